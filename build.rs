@@ -0,0 +1,46 @@
+//! Generates the C header of the `ffi` feature in `OUT_DIR`
+//!
+//! The committed `include/game_2048_model.h` is a copy of it, a test of the
+//! `ffi` module fails when the copy is stale.
+
+fn main() {
+    #[cfg(feature = "ffi")]
+    header::write();
+}
+
+#[cfg(feature = "ffi")]
+mod header {
+    use std::env;
+    use std::path::Path;
+
+    use cbindgen::{Builder, Config, DocumentationLength, EnumConfig, Language, RenameRule, Style};
+
+    pub fn write() {
+        println!("cargo:rerun-if-changed=src/ffi");
+        let out_dir = env::var("OUT_DIR").expect("Cargo sets OUT_DIR for build scripts");
+
+        let mut config = Config {
+            language: Language::C,
+            include_guard: Some("GAME_2048_MODEL_H".to_string()),
+            header: Some("/* Generated by build.rs from src/ffi, do not edit */".to_string()),
+            style: Style::Type,
+            documentation: true,
+            // The full comments hold Rust examples, only their summary is useful in C
+            documentation_length: DocumentationLength::Short,
+            cpp_compat: true,
+            usize_is_size_t: true,
+            ..Config::default()
+        };
+        config.enumeration = EnumConfig {
+            rename_variants: RenameRule::QualifiedScreamingSnakeCase,
+            ..EnumConfig::default()
+        };
+
+        Builder::new()
+            .with_config(config)
+            .with_src("src/ffi/game.rs")
+            .generate()
+            .expect("The ffi module can be read by cbindgen")
+            .write_to_file(Path::new(&out_dir).join("game_2048_model.h"));
+    }
+}
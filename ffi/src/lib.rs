@@ -0,0 +1,8 @@
+//! The shared library of the C API of `game_2048_model`
+//!
+//! Building this crate produces `libgame_2048_model_ffi.so` (or the `.dll` or
+//! `.dylib` of the platform) with every function of
+//! [`game_2048_model::ffi`], declared in the header
+//! `include/game_2048_model.h`.
+
+pub use game_2048_model::ffi::*;
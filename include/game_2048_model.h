@@ -0,0 +1,121 @@
+/* Generated by build.rs from src/ffi, do not edit */
+
+#ifndef GAME_2048_MODEL_H
+#define GAME_2048_MODEL_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Slide towards the top
+ */
+#define G2048_UP 0
+
+/**
+ * Slide towards the right
+ */
+#define G2048_RIGHT 1
+
+/**
+ * Slide towards the bottom
+ */
+#define G2048_DOWN 2
+
+/**
+ * Slide towards the left
+ */
+#define G2048_LEFT 3
+
+/**
+ * The number of squares of a board, the size of the buffer given to [`g2048_board`]
+ */
+#define G2048_SQUARES 16
+
+/**
+ * The result of a call, every value but `Ok` means nothing changed
+ */
+typedef enum {
+  /**
+   * The call succeeded
+   */
+  G2048_STATUS_OK = 0,
+  /**
+   * The slide does not change the board
+   */
+  G2048_STATUS_UNCHANGED = 1,
+  /**
+   * There is no empty square to spawn a number on, see [`NoEmptyError`]
+   */
+  G2048_STATUS_NO_EMPTY_SQUARE = 2,
+  /**
+   * A pointer argument was null
+   */
+  G2048_STATUS_NULL_POINTER = 3,
+  /**
+   * An argument is out of range, like a direction above 3 or a small buffer
+   */
+  G2048_STATUS_INVALID_ARGUMENT = 4,
+} G2048Status;
+
+/**
+ * An opaque game handle, created by [`g2048_new`] and freed by [`g2048_free`]
+ */
+typedef struct G2048Game G2048Game;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Start a new game with two spawned numbers
+ */
+G2048Game *g2048_new(uint64_t seed);
+
+/**
+ * Load a game from the exponents of its numbers in array form
+ */
+G2048Game *g2048_from_board(const uint8_t *board, uint64_t seed);
+
+/**
+ * Free a game, does nothing if it is null
+ */
+void g2048_free(G2048Game *game);
+
+/**
+ * Slide towards a direction, [`G2048_UP`] to [`G2048_LEFT`]
+ */
+G2048Status g2048_slide(G2048Game *game, uint32_t direction);
+
+/**
+ * Spawn a 2 or a 4 on a random empty square
+ */
+G2048Status g2048_spawn(G2048Game *game);
+
+/**
+ * Copy the exponents of the numbers in array form into a buffer
+ */
+G2048Status g2048_board(const G2048Game *game, uint8_t *buffer, size_t length);
+
+/**
+ * The score gained by merges, 0 for a null handle
+ */
+uint64_t g2048_score(const G2048Game *game);
+
+/**
+ * Whether no direction changes the board, true for a null handle
+ */
+bool g2048_is_over(const G2048Game *game);
+
+/**
+ * A static, null terminated description of a status code
+ */
+const char *g2048_status_message(int32_t status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* GAME_2048_MODEL_H */
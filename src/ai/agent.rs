@@ -0,0 +1,363 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use rand::prelude::*;
+
+use super::heuristics::Heuristic;
+use super::{Expectimax, IterativeDeepening, Mcts, MonteCarlo, ParallelExpectimax};
+use crate::base::*;
+
+/// A strategy choosing which direction to play
+///
+/// The trait is generic over the game model so agents can be used as trait
+/// objects for a single model, like `Box<dyn Agent<Matrix>>`.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::{Agent, Greedy, PreferenceOrder};
+/// use game_2048_model::models::{Matrix, Model};
+///
+/// let mut agents: Vec<Box<dyn Agent<Matrix>>> = vec![
+///     Box::new(PreferenceOrder::corner()),
+///     Box::new(Greedy),
+/// ];
+/// let game = Matrix::from([
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     1,1,0,0
+/// ]);
+///
+/// for agent in agents.iter_mut() {
+///     assert!(agent.choose(&game).is_some());
+/// }
+/// ```
+///
+pub trait Agent<M: Model> {
+    /// Returns the direction to play, or `None` if no direction changes the board
+    fn choose(&mut self, game: &M) -> Option<Directions>;
+}
+
+/// The directions that change the board, in the order of [`DIRECTIONS`]
+fn legal_directions<M: Model>(game: &M) -> impl Iterator<Item = Directions> {
+    let board = game.as_array();
+    DIRECTIONS
+        .iter()
+        .copied()
+        .filter(move |direction| M::from(board).slide(*direction).is_some())
+}
+
+/// Plays a uniformly random direction among the ones that change the board
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::{Agent, RandomAgent};
+/// use game_2048_model::models::{ArrayModel, Directions};
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut agent = RandomAgent::new(StdRng::seed_from_u64(42));
+/// let game = ArrayModel::from([
+///     0,2,1,2,
+///     0,1,2,1,
+///     0,2,1,2,
+///     0,1,2,1
+/// ]);
+///
+/// assert_eq!(agent.choose(&game), Some(Directions::Left));
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct RandomAgent<R> {
+    rng: R,
+}
+
+impl<R: Rng> RandomAgent<R> {
+    /// Create an agent drawing directions from the generator
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::RandomAgent;
+    /// use rand::thread_rng;
+    ///
+    /// let agent = RandomAgent::new(thread_rng());
+    /// ```
+    ///
+    pub fn new(rng: R) -> Self {
+        RandomAgent { rng }
+    }
+}
+
+impl<M: Model, R: Rng> Agent<M> for RandomAgent<R> {
+    fn choose(&mut self, game: &M) -> Option<Directions> {
+        let legal: Vec<Directions> = legal_directions(game).collect();
+        legal.choose(&mut self.rng).copied()
+    }
+}
+
+/// Plays the first direction in a fixed order that changes the board
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::{Agent, PreferenceOrder};
+/// use game_2048_model::models::{ArrayModel, Directions};
+///
+/// let mut agent = PreferenceOrder::new([
+///     Directions::Up,
+///     Directions::Left,
+///     Directions::Right,
+///     Directions::Down,
+/// ]);
+/// let game = ArrayModel::from([
+///     1,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0
+/// ]);
+///
+/// assert_eq!(agent.choose(&game), Some(Directions::Right));
+/// ```
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PreferenceOrder {
+    order: [Directions; 4],
+}
+
+impl PreferenceOrder {
+    /// Create an agent trying the directions in the given order
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::PreferenceOrder;
+    /// use game_2048_model::models::DIRECTIONS;
+    ///
+    /// let agent = PreferenceOrder::new(DIRECTIONS);
+    /// ```
+    ///
+    pub fn new(order: [Directions; 4]) -> Self {
+        PreferenceOrder { order }
+    }
+
+    /// The corner strategy, keeping the numbers in the bottom left corner
+    ///
+    /// Tries Down, Left, Right and then Up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Agent, PreferenceOrder};
+    /// use game_2048_model::models::{ArrayModel, Directions};
+    ///
+    /// let game = ArrayModel::from([
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     0,1,0,0
+    /// ]);
+    ///
+    /// assert_eq!(PreferenceOrder::corner().choose(&game), Some(Directions::Left));
+    /// ```
+    ///
+    pub fn corner() -> Self {
+        PreferenceOrder::new([
+            Directions::Down,
+            Directions::Left,
+            Directions::Right,
+            Directions::Up,
+        ])
+    }
+}
+
+impl<M: Model> Agent<M> for PreferenceOrder {
+    fn choose(&mut self, game: &M) -> Option<Directions> {
+        let board = game.as_array();
+        self.order
+            .iter()
+            .copied()
+            .find(|direction| M::from(board).slide(*direction).is_some())
+    }
+}
+
+/// Plays the direction gaining the most score right away
+///
+/// Ties are broken in the order of [`DIRECTIONS`].
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::{Agent, Greedy};
+/// use game_2048_model::models::{ArrayModel, Directions};
+///
+/// let game = ArrayModel::from([
+///     1,0,0,0,
+///     1,0,0,0,
+///     2,2,0,0,
+///     0,0,0,0
+/// ]);
+///
+/// assert_eq!(Greedy.choose(&game), Some(Directions::Right));
+/// ```
+///
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Greedy;
+
+impl<M: Model> Agent<M> for Greedy {
+    fn choose(&mut self, game: &M) -> Option<Directions> {
+        let board = game.as_array();
+        let mut best: Option<(Directions, u64)> = None;
+        for direction in legal_directions(game) {
+            let mut child = M::from(board);
+            child.slide(direction);
+            let score = slide_score(&board, &child.as_array());
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((direction, score));
+            }
+        }
+        best.map(|(direction, _)| direction)
+    }
+}
+
+impl<M: Model, E: Heuristic> Agent<M> for Expectimax<E> {
+    fn choose(&mut self, game: &M) -> Option<Directions> {
+        self.best_direction(game)
+    }
+}
+
+impl<M: Model + 'static, E: Heuristic + Send + Sync + 'static> Agent<M> for ParallelExpectimax<E> {
+    fn choose(&mut self, game: &M) -> Option<Directions> {
+        self.best_direction(game)
+    }
+}
+
+impl<M: Model, E: Heuristic> Agent<M> for IterativeDeepening<E> {
+    fn choose(&mut self, game: &M) -> Option<Directions> {
+        self.best_direction(game)
+    }
+}
+
+impl<M: Model, R: Rng> Agent<M> for MonteCarlo<R> {
+    fn choose(&mut self, game: &M) -> Option<Directions> {
+        self.best_direction(game)
+    }
+}
+
+impl<M: Model, R: Rng> Agent<M> for Mcts<R> {
+    fn choose(&mut self, game: &M) -> Option<Directions> {
+        self.best_direction(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Agent, Greedy, PreferenceOrder, RandomAgent};
+    use crate::models::{ArrayModel, Directions, Matrix, Model};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[rustfmt::skip]
+    const DEAD: [u8; 16] = [
+        1,2,1,2,
+        2,1,2,1,
+        1,2,1,2,
+        2,1,2,1
+    ];
+
+    mod random_agent {
+        use super::*;
+
+        #[test]
+        fn returns_none_without_legal_moves() {
+            let mut agent = RandomAgent::new(StdRng::seed_from_u64(1));
+
+            assert_eq!(agent.choose(&ArrayModel::from(DEAD)), None);
+        }
+
+        #[test]
+        fn only_plays_legal_moves() {
+            let mut agent = RandomAgent::new(StdRng::seed_from_u64(1));
+            let game = Matrix::from([1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+            for _ in 0..50 {
+                let direction = agent.choose(&game);
+                assert!(
+                    direction == Some(Directions::Right) || direction == Some(Directions::Down)
+                );
+            }
+        }
+    }
+
+    mod preference_order {
+        use super::*;
+
+        #[test]
+        fn returns_none_without_legal_moves() {
+            assert_eq!(PreferenceOrder::corner().choose(&Matrix::from(DEAD)), None);
+        }
+
+        #[test]
+        fn prefers_earlier_directions() {
+            let game = ArrayModel::from([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+            assert_eq!(
+                PreferenceOrder::corner().choose(&game),
+                Some(Directions::Left)
+            );
+        }
+    }
+
+    mod greedy {
+        use super::*;
+
+        #[test]
+        fn returns_none_without_legal_moves() {
+            assert_eq!(Greedy.choose(&ArrayModel::from(DEAD)), None);
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn breaks_ties_in_direction_order() {
+            let game = ArrayModel::from([
+                0,0,0,0,
+                0,0,0,0,
+                0,0,0,0,
+                0,0,0,1
+            ]);
+
+            assert_eq!(Greedy.choose(&game), Some(Directions::Up));
+        }
+    }
+
+    mod trait_objects {
+        use super::*;
+        use crate::ai::Expectimax;
+
+        #[test]
+        fn all_agents_play_a_game() {
+            let mut agents: Vec<Box<dyn Agent<ArrayModel>>> = vec![
+                Box::new(RandomAgent::new(StdRng::seed_from_u64(1))),
+                Box::new(PreferenceOrder::corner()),
+                Box::new(Greedy),
+                Box::new(Expectimax::default().depth(1)),
+            ];
+
+            for agent in agents.iter_mut() {
+                let mut rng = StdRng::seed_from_u64(2);
+                let mut game = ArrayModel::new();
+                game.random(&mut rng).unwrap();
+                game.random(&mut rng).unwrap();
+                let mut moves = 0;
+                while let Some(direction) = agent.choose(&game) {
+                    assert!(game.slide(direction).is_some());
+                    game.random(&mut rng).unwrap();
+                    moves += 1;
+                }
+                assert!(moves > 0);
+            }
+        }
+    }
+}
@@ -0,0 +1,613 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use super::Agent;
+use crate::base::*;
+
+/// The smallest and largest exponent whose reach rate is always displayed, from 64 to 32768
+const DISPLAYED_EXPONENTS: (BoardElement, BoardElement) = (6, 15);
+
+/// The outcome of a single game played by [`Evaluation`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GameResult {
+    /// The seed used for the spawns and to create the agent
+    pub seed: u64,
+    /// The score gained by merges
+    pub score: u64,
+    /// The number of slides played
+    pub moves: usize,
+    /// The largest exponent on the final board
+    pub max_exponent: BoardElement,
+}
+
+/// Play a single game from a seed until the agent has no direction left
+///
+/// The game starts with two spawned numbers. It ends when the agent returns
+/// `None` or a direction that does not change the board.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::{play_game, PreferenceOrder};
+/// use game_2048_model::models::ArrayModel;
+///
+/// let result = play_game::<ArrayModel, _>(&mut PreferenceOrder::corner(), 42);
+///
+/// assert_eq!(result.seed, 42);
+/// assert!(result.moves > 0);
+/// ```
+///
+pub fn play_game<M: Model, A: Agent<M> + ?Sized>(agent: &mut A, seed: u64) -> GameResult {
+    play_moves::<M, _, _>(agent, seed, |_| {})
+}
+
+/// Play a game like [`play_game`], calling `played` with each move that changed the board
+pub(crate) fn play_moves<M, A, F>(agent: &mut A, seed: u64, mut played: F) -> GameResult
+where
+    M: Model,
+    A: Agent<M> + ?Sized,
+    F: FnMut(Directions),
+{
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut game = M::new();
+    let _ = game.random(&mut rng);
+    let _ = game.random(&mut rng);
+
+    let mut score = 0;
+    let mut moves = 0;
+    while let Some(direction) = agent.choose(&game) {
+        let before = game.as_array();
+        if game.slide(direction).is_none() {
+            break;
+        }
+        score += slide_score(&before, &game.as_array());
+        moves += 1;
+        played(direction);
+        let _ = game.random(&mut rng);
+    }
+
+    GameResult {
+        seed,
+        score,
+        moves,
+        max_exponent: game.as_array().iter().max().copied().unwrap_or(0),
+    }
+}
+
+/// Plays many seeded games with an agent and collects statistics
+///
+/// Game `i` uses seed `seed + i`, wrapping around after `u64::MAX`, both for
+/// its spawns and to create its agent, so the results are the same no matter
+/// how many threads are used.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::{Evaluation, Greedy};
+/// use game_2048_model::models::ArrayModel;
+///
+/// let report = Evaluation::new(20).seed(1000).threads(2).run::<ArrayModel, _, _>(|_| Greedy);
+///
+/// assert_eq!(report.games().len(), 20);
+/// println!("{}", report);
+/// ```
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Evaluation {
+    games: usize,
+    seed: u64,
+    threads: usize,
+}
+
+impl Evaluation {
+    /// Create an evaluation of `games` games starting from seed 0 on a single thread
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::Evaluation;
+    ///
+    /// let evaluation = Evaluation::new(100);
+    /// ```
+    ///
+    pub fn new(games: usize) -> Self {
+        Evaluation {
+            games,
+            seed: 0,
+            threads: 1,
+        }
+    }
+
+    /// Set the seed of the first game
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::Evaluation;
+    ///
+    /// let evaluation = Evaluation::new(100).seed(5000);
+    /// ```
+    ///
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Set the number of threads playing games, at least 1
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::Evaluation;
+    ///
+    /// let evaluation = Evaluation::new(100).threads(8);
+    /// ```
+    ///
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Play every game, creating a new agent for each game from its seed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Evaluation, RandomAgent};
+    /// use game_2048_model::models::Matrix;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let report = Evaluation::new(10)
+    ///     .run::<Matrix, _, _>(|seed| RandomAgent::new(StdRng::seed_from_u64(seed)));
+    ///
+    /// assert!(report.mean_score() > 0.0);
+    /// ```
+    ///
+    pub fn run<M, A, F>(&self, make_agent: F) -> Report
+    where
+        M: Model,
+        A: Agent<M>,
+        F: Fn(u64) -> A + Sync,
+    {
+        let start = Instant::now();
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(self.games));
+
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(self.games.max(1)) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= self.games {
+                        break;
+                    }
+                    let seed = self.seed.wrapping_add(index as u64);
+                    let mut agent = make_agent(seed);
+                    let result = play_game::<M, A>(&mut agent, seed);
+                    results.lock().expect("A game thread panicked").push(result);
+                });
+            }
+        });
+
+        let mut games = results.into_inner().expect("A game thread panicked");
+        games.sort_by_key(|result| result.seed);
+        Report {
+            games,
+            duration: start.elapsed(),
+        }
+    }
+}
+
+/// Statistics over the games played by [`Evaluation::run`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    games: Vec<GameResult>,
+    duration: Duration,
+}
+
+impl Report {
+    /// Create a report from already played games
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{GameResult, Report};
+    /// use std::time::Duration;
+    ///
+    /// let report = Report::new(
+    ///     vec![GameResult { seed: 0, score: 100, moves: 10, max_exponent: 5 }],
+    ///     Duration::from_secs(1),
+    /// );
+    ///
+    /// assert_eq!(report.moves_per_second(), 10.0);
+    /// ```
+    ///
+    pub fn new(games: Vec<GameResult>, duration: Duration) -> Self {
+        Report { games, duration }
+    }
+
+    /// Every game ordered by seed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Evaluation, Greedy};
+    /// use game_2048_model::models::ArrayModel;
+    ///
+    /// let report = Evaluation::new(3).seed(7).run::<ArrayModel, _, _>(|_| Greedy);
+    ///
+    /// assert_eq!(report.games()[2].seed, 9);
+    /// ```
+    ///
+    pub fn games(&self) -> &[GameResult] {
+        &self.games
+    }
+
+    /// The wall clock time spent playing
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Evaluation, Greedy};
+    /// use game_2048_model::models::ArrayModel;
+    ///
+    /// let report = Evaluation::new(3).run::<ArrayModel, _, _>(|_| Greedy);
+    ///
+    /// assert!(report.duration().as_secs() < 60);
+    /// ```
+    ///
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    fn scores(&self) -> Vec<f64> {
+        self.games.iter().map(|game| game.score as f64).collect()
+    }
+
+    /// The average score, 0 without games
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{GameResult, Report};
+    /// use std::time::Duration;
+    ///
+    /// let games = vec![
+    ///     GameResult { seed: 0, score: 100, moves: 10, max_exponent: 5 },
+    ///     GameResult { seed: 1, score: 300, moves: 30, max_exponent: 6 },
+    /// ];
+    ///
+    /// assert_eq!(Report::new(games, Duration::from_secs(1)).mean_score(), 200.0);
+    /// ```
+    ///
+    pub fn mean_score(&self) -> f64 {
+        mean(&self.scores())
+    }
+
+    /// The median score, 0 without games
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{GameResult, Report};
+    /// use std::time::Duration;
+    ///
+    /// let games = vec![
+    ///     GameResult { seed: 0, score: 100, moves: 10, max_exponent: 5 },
+    ///     GameResult { seed: 1, score: 300, moves: 30, max_exponent: 6 },
+    ///     GameResult { seed: 2, score: 1000, moves: 80, max_exponent: 7 },
+    /// ];
+    ///
+    /// assert_eq!(Report::new(games, Duration::from_secs(1)).median_score(), 300.0);
+    /// ```
+    ///
+    pub fn median_score(&self) -> f64 {
+        let mut scores = self.scores();
+        if scores.is_empty() {
+            return 0.0;
+        }
+        scores.sort_by(|a, b| a.partial_cmp(b).expect("Scores are never NaN"));
+        let middle = scores.len() / 2;
+        if scores.len().is_multiple_of(2) {
+            (scores[middle - 1] + scores[middle]) / 2.0
+        } else {
+            scores[middle]
+        }
+    }
+
+    /// The population standard deviation of the score, 0 without games
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{GameResult, Report};
+    /// use std::time::Duration;
+    ///
+    /// let games = vec![
+    ///     GameResult { seed: 0, score: 100, moves: 10, max_exponent: 5 },
+    ///     GameResult { seed: 1, score: 300, moves: 30, max_exponent: 6 },
+    /// ];
+    ///
+    /// assert_eq!(Report::new(games, Duration::from_secs(1)).score_std_dev(), 100.0);
+    /// ```
+    ///
+    pub fn score_std_dev(&self) -> f64 {
+        let scores = self.scores();
+        let mean = mean(&scores);
+        let variance = mean_of(
+            scores.iter().map(|score| (score - mean).powi(2)),
+            scores.len(),
+        );
+        variance.sqrt()
+    }
+
+    /// The average number of slides per game, 0 without games
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{GameResult, Report};
+    /// use std::time::Duration;
+    ///
+    /// let games = vec![
+    ///     GameResult { seed: 0, score: 100, moves: 10, max_exponent: 5 },
+    ///     GameResult { seed: 1, score: 300, moves: 30, max_exponent: 6 },
+    /// ];
+    ///
+    /// assert_eq!(Report::new(games, Duration::from_secs(1)).mean_moves(), 20.0);
+    /// ```
+    ///
+    pub fn mean_moves(&self) -> f64 {
+        mean_of(
+            self.games.iter().map(|game| game.moves as f64),
+            self.games.len(),
+        )
+    }
+
+    /// Slides played per second of wall clock time, over all threads
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{GameResult, Report};
+    /// use std::time::Duration;
+    ///
+    /// let games = vec![GameResult { seed: 0, score: 100, moves: 10, max_exponent: 5 }];
+    ///
+    /// assert_eq!(Report::new(games, Duration::from_millis(500)).moves_per_second(), 20.0);
+    /// ```
+    ///
+    pub fn moves_per_second(&self) -> f64 {
+        let moves: usize = self.games.iter().map(|game| game.moves).sum();
+        let seconds = self.duration.as_secs_f64();
+        if seconds > 0.0 {
+            moves as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// The number of games ending with each largest exponent
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{GameResult, Report};
+    /// use std::time::Duration;
+    ///
+    /// let games = vec![
+    ///     GameResult { seed: 0, score: 100, moves: 10, max_exponent: 5 },
+    ///     GameResult { seed: 1, score: 300, moves: 30, max_exponent: 5 },
+    /// ];
+    /// let distribution = Report::new(games, Duration::from_secs(1)).max_exponents();
+    ///
+    /// assert_eq!(distribution.get(&5), Some(&2));
+    /// ```
+    ///
+    pub fn max_exponents(&self) -> BTreeMap<BoardElement, usize> {
+        let mut distribution = BTreeMap::new();
+        for game in self.games.iter() {
+            *distribution.entry(game.max_exponent).or_insert(0) += 1;
+        }
+        distribution
+    }
+
+    /// The fraction of games, between 0 and 1, reaching at least the exponent
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{GameResult, Report};
+    /// use std::time::Duration;
+    ///
+    /// let games = vec![
+    ///     GameResult { seed: 0, score: 20000, moves: 1000, max_exponent: 11 },
+    ///     GameResult { seed: 1, score: 300, moves: 30, max_exponent: 6 },
+    /// ];
+    ///
+    /// // Half of the games reached 2048.
+    /// assert_eq!(Report::new(games, Duration::from_secs(1)).reach_rate(11), 0.5);
+    /// ```
+    ///
+    pub fn reach_rate(&self, exponent: BoardElement) -> f64 {
+        let reached = self
+            .games
+            .iter()
+            .filter(|game| game.max_exponent >= exponent)
+            .count();
+        mean_of(std::iter::once(reached as f64), self.games.len())
+    }
+}
+
+fn mean_of<I: Iterator<Item = f64>>(values: I, count: usize) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        values.sum::<f64>() / count as f64
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    mean_of(values.iter().copied(), values.len())
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "games: {}", self.games.len())?;
+        writeln!(
+            f,
+            "score: mean {:.1}, median {:.1}, std dev {:.1}",
+            self.mean_score(),
+            self.median_score(),
+            self.score_std_dev()
+        )?;
+        writeln!(
+            f,
+            "moves: {:.1} per game, {:.1} per second",
+            self.mean_moves(),
+            self.moves_per_second()
+        )?;
+        writeln!(f, "reached:")?;
+        let (first, last) = DISPLAYED_EXPONENTS;
+        let highest = self
+            .max_exponents()
+            .keys()
+            .next_back()
+            .copied()
+            .unwrap_or(0);
+        for exponent in first..=last.max(highest) {
+            writeln!(
+                f,
+                "  {:>6} {:>6.1}%",
+                1u64 << exponent,
+                100.0 * self.reach_rate(exponent)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{play_game, Evaluation, GameResult, Report};
+    use crate::ai::{Greedy, PreferenceOrder, RandomAgent};
+    use crate::models::{ArrayModel, Matrix};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::time::Duration;
+
+    mod play_game {
+        use super::*;
+
+        #[test]
+        fn same_seed_same_game() {
+            let first = play_game::<ArrayModel, _>(&mut PreferenceOrder::corner(), 3);
+            let second = play_game::<ArrayModel, _>(&mut PreferenceOrder::corner(), 3);
+
+            assert_eq!(first, second);
+        }
+    }
+
+    mod run {
+        use super::*;
+
+        #[test]
+        fn results_do_not_depend_on_threads() {
+            let make_agent = |seed| RandomAgent::new(StdRng::seed_from_u64(seed));
+            let single = Evaluation::new(12)
+                .seed(40)
+                .run::<ArrayModel, _, _>(make_agent);
+            let parallel = Evaluation::new(12)
+                .seed(40)
+                .threads(4)
+                .run::<ArrayModel, _, _>(make_agent);
+
+            assert_eq!(single.games(), parallel.games());
+        }
+
+        #[test]
+        fn plays_every_seed_once() {
+            let report = Evaluation::new(9)
+                .seed(100)
+                .threads(3)
+                .run::<Matrix, _, _>(|_| Greedy);
+            let seeds: Vec<u64> = report.games().iter().map(|game| game.seed).collect();
+
+            assert_eq!(seeds, (100..109).collect::<Vec<u64>>());
+        }
+
+        #[test]
+        fn seeds_wrap_around() {
+            let report = Evaluation::new(2)
+                .seed(u64::MAX)
+                .run::<ArrayModel, _, _>(|_| Greedy);
+            let seeds: Vec<u64> = report.games().iter().map(|game| game.seed).collect();
+
+            assert_eq!(seeds, vec![0, u64::MAX]);
+        }
+
+        #[test]
+        fn no_games() {
+            let report = Evaluation::new(0)
+                .threads(4)
+                .run::<ArrayModel, _, _>(|_| Greedy);
+
+            assert!(report.games().is_empty());
+            assert_eq!(report.mean_score(), 0.0);
+            assert_eq!(report.median_score(), 0.0);
+            assert_eq!(report.reach_rate(11), 0.0);
+        }
+    }
+
+    mod report {
+        use super::*;
+
+        #[test]
+        fn displays_reached_tiles() {
+            let games = vec![
+                GameResult {
+                    seed: 0,
+                    score: 20000,
+                    moves: 1000,
+                    max_exponent: 11,
+                },
+                GameResult {
+                    seed: 1,
+                    score: 300,
+                    moves: 30,
+                    max_exponent: 6,
+                },
+            ];
+            let text = Report::new(games, Duration::from_secs(1)).to_string();
+
+            assert!(text.contains("games: 2"));
+            assert!(text.contains("  2048   50.0%"));
+            assert!(text.contains("    64  100.0%"));
+            assert!(text.contains("  1024   50.0%"));
+            assert!(text.contains(" 32768    0.0%"));
+        }
+
+        #[test]
+        fn displays_tiles_above_the_fixed_range() {
+            let games = vec![GameResult {
+                seed: 0,
+                score: 3_000_000,
+                moves: 60000,
+                max_exponent: 17,
+            }];
+            let text = Report::new(games, Duration::from_secs(1)).to_string();
+
+            assert!(text.contains("131072  100.0%"));
+        }
+    }
+}
@@ -0,0 +1,494 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use super::heuristics::{Heuristic, Weighted};
+use crate::base::*;
+
+/// Expectimax search over any game [`Model`]
+///
+/// The player nodes pick the direction with the highest expected value and the
+/// chance nodes average over every empty square with the spawn probabilities
+/// used by [`Model::random`]. Leaves are scored by the evaluation function,
+/// any [`Heuristic`] or closure taking an [`ArrayBoard`]. Boards where no
+/// direction is legal are scored far below every board still in play.
+#[derive(Debug, Clone)]
+pub struct Expectimax<E> {
+    pub(super) depth: usize,
+    pub(super) probability_cutoff: f64,
+    pub(super) evaluation: E,
+}
+
+/// Subtracted from the evaluation of a board without any legal move, so that
+/// losing is worth less than any board still in play
+const GAME_OVER_PENALTY: f64 = 1.0e9;
+
+/// The value of a node and whether the probability cutoff was used at or below it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(super) struct NodeValue {
+    pub(super) value: f64,
+    pub(super) pruned: bool,
+}
+
+/// Hooks into the recursion of [`Expectimax`] for the searches built on it
+///
+/// The plain search uses `()`, which never stops and stores nothing.
+pub(super) trait Visitor {
+    /// Called on entering every node, returns `None` to stop the search
+    fn visit(&mut self) -> Option<()> {
+        Some(())
+    }
+
+    /// Called when a chance node is evaluated because the depth ran out
+    fn depth_limited(&mut self) {}
+
+    /// A value stored for the chance node by [`Visitor::store`]
+    fn lookup(
+        &mut self,
+        _board: &ArrayBoard,
+        _depth: usize,
+        _probability: f64,
+    ) -> Option<NodeValue> {
+        None
+    }
+
+    /// Called with the value of every expanded chance node
+    fn store(&mut self, _board: &ArrayBoard, _depth: usize, _probability: f64, _value: NodeValue) {}
+}
+
+impl Visitor for () {}
+
+/// Every spawn on the board, with the probability of the spawned number and
+/// the probability of reaching the spawned board
+pub(super) fn spawns(
+    board: ArrayBoard,
+    probability: f64,
+) -> impl Iterator<Item = (ArrayBoard, f64, f64)> {
+    let empty = board.iter().filter(|x| **x == 0).count();
+    (0..board.len())
+        .filter(move |square| board[*square] == 0)
+        .flat_map(move |square| {
+            SPAWN_PROBABILITIES
+                .iter()
+                .map(move |(exponent, spawn_probability)| {
+                    let mut child = board;
+                    child[square] = *exponent;
+                    let child_probability = probability * spawn_probability / empty as f64;
+                    (child, *spawn_probability, child_probability)
+                })
+        })
+}
+
+/// The direction with the highest value, ties broken in the order of [`DIRECTIONS`]
+pub(crate) fn best_of(values: &[Option<f64>; 4]) -> Option<Directions> {
+    let mut best: Option<(Directions, f64)> = None;
+    for (direction, value) in DIRECTIONS.iter().zip(values.iter()) {
+        if let Some(value) = *value {
+            if best.is_none_or(|(_, best_value)| value > best_value) {
+                best = Some((*direction, value));
+            }
+        }
+    }
+    best.map(|(direction, _)| direction)
+}
+
+impl<E> Expectimax<E>
+where
+    E: Heuristic,
+{
+    /// Create a new search with the given evaluation function
+    ///
+    /// The search defaults to a depth of 3 moves and a probability cutoff of 0.0001.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::Expectimax;
+    /// use game_2048_model::ai::heuristics::Weighted;
+    ///
+    /// let search = Expectimax::new(Weighted::standard());
+    /// ```
+    ///
+    pub fn new(evaluation: E) -> Self {
+        Expectimax {
+            depth: 3,
+            probability_cutoff: 0.0001,
+            evaluation,
+        }
+    }
+
+    /// Set the number of moves the search looks ahead
+    ///
+    /// A depth of 1 only evaluates the board directly after each move.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `depth` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::Expectimax;
+    /// use game_2048_model::ArrayBoard;
+    ///
+    /// let search = Expectimax::new(|_: &ArrayBoard| 0.0).depth(2);
+    /// ```
+    ///
+    pub fn depth(mut self, depth: usize) -> Self {
+        assert!(depth > 0, "The search depth must be at least 1");
+        self.depth = depth;
+        self
+    }
+
+    /// Set the probability below which a chance node is evaluated instead of expanded
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::Expectimax;
+    /// use game_2048_model::ArrayBoard;
+    ///
+    /// let search = Expectimax::new(|_: &ArrayBoard| 0.0).probability_cutoff(0.001);
+    /// ```
+    ///
+    pub fn probability_cutoff(mut self, probability_cutoff: f64) -> Self {
+        self.probability_cutoff = probability_cutoff;
+        self
+    }
+
+    /// Returns the best direction to play, or `None` if no direction changes the board
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::Expectimax;
+    /// use game_2048_model::models::{Directions, Matrix};
+    /// use game_2048_model::ArrayBoard;
+    ///
+    /// let game = Matrix::from([
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     1,2,3,4
+    /// ]);
+    /// let search = Expectimax::new(|board: &ArrayBoard| {
+    ///     board.iter().filter(|x| **x == 0).count() as f64
+    /// });
+    ///
+    /// assert_eq!(search.best_direction(&game), Some(Directions::Up));
+    /// ```
+    ///
+    pub fn best_direction<M: Model>(&self, game: &M) -> Option<Directions> {
+        best_of(&self.evaluate(game))
+    }
+
+    /// Returns the expected value of each direction in the order of [`DIRECTIONS`]
+    ///
+    /// Directions that do not change the board are `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::Expectimax;
+    /// use game_2048_model::models::Matrix;
+    /// use game_2048_model::ArrayBoard;
+    ///
+    /// let game = Matrix::from([
+    ///     1,0,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,0
+    /// ]);
+    /// let values = Expectimax::new(|_: &ArrayBoard| 1.0).depth(1).evaluate(&game);
+    ///
+    /// assert_eq!(values, [None, Some(1.0), Some(1.0), None]);
+    /// ```
+    ///
+    pub fn evaluate<M: Model>(&self, game: &M) -> [Option<f64>; 4] {
+        self.root::<M, _>(&mut (), game.as_array(), self.depth)
+            .expect("The plain search is never stopped")
+    }
+
+    /// The expected value of each direction searched to the given depth
+    pub(super) fn root<M: Model, V: Visitor>(
+        &self,
+        visitor: &mut V,
+        board: ArrayBoard,
+        depth: usize,
+    ) -> Option<[Option<f64>; 4]> {
+        let mut values = [None; 4];
+        for (value, direction) in values.iter_mut().zip(DIRECTIONS.iter()) {
+            let mut child = M::from(board);
+            if child.slide(*direction).is_some() {
+                let node = self.chance_node::<M, V>(visitor, child.as_array(), depth - 1, 1.0)?;
+                *value = Some(node.value);
+            }
+        }
+        Some(values)
+    }
+
+    /// Value of the board when it is the player's turn to move
+    ///
+    /// A board without any legal move is worth its evaluation minus
+    /// [`GAME_OVER_PENALTY`].
+    pub(super) fn max_node<M: Model, V: Visitor>(
+        &self,
+        visitor: &mut V,
+        board: ArrayBoard,
+        depth: usize,
+        probability: f64,
+    ) -> Option<NodeValue> {
+        visitor.visit()?;
+        let mut best: Option<f64> = None;
+        let mut pruned = false;
+        for direction in DIRECTIONS.iter() {
+            let mut child = M::from(board);
+            if child.slide(*direction).is_some() {
+                let node =
+                    self.chance_node::<M, V>(visitor, child.as_array(), depth - 1, probability)?;
+                pruned |= node.pruned;
+                if best.is_none_or(|best_value| node.value > best_value) {
+                    best = Some(node.value);
+                }
+            }
+        }
+        Some(NodeValue {
+            value: best.unwrap_or_else(|| self.evaluation.score(&board) - GAME_OVER_PENALTY),
+            pruned,
+        })
+    }
+
+    /// The value of a chance node that is evaluated instead of expanded, or
+    /// `None` if it has to be expanded
+    pub(super) fn chance_leaf<V: Visitor>(
+        &self,
+        visitor: &mut V,
+        board: &ArrayBoard,
+        depth: usize,
+        probability: f64,
+    ) -> Option<NodeValue> {
+        let pruned = if depth == 0 {
+            visitor.depth_limited();
+            false
+        } else if probability < self.probability_cutoff {
+            true
+        } else if board.iter().all(|x| *x != 0) {
+            false
+        } else {
+            return None;
+        };
+        Some(NodeValue {
+            value: self.evaluation.score(board),
+            pruned,
+        })
+    }
+
+    /// Expected value of the board over every possible spawn
+    pub(super) fn chance_node<M: Model, V: Visitor>(
+        &self,
+        visitor: &mut V,
+        board: ArrayBoard,
+        depth: usize,
+        probability: f64,
+    ) -> Option<NodeValue> {
+        visitor.visit()?;
+        if let Some(leaf) = self.chance_leaf(visitor, &board, depth, probability) {
+            return Some(leaf);
+        }
+        if let Some(stored) = visitor.lookup(&board, depth, probability) {
+            return Some(stored);
+        }
+
+        let empty = board.iter().filter(|x| **x == 0).count();
+        let mut total = 0.0;
+        let mut pruned = false;
+        for (child, spawn_probability, child_probability) in spawns(board, probability) {
+            let node = self.max_node::<M, V>(visitor, child, depth, child_probability)?;
+            pruned |= node.pruned;
+            total += spawn_probability * node.value;
+        }
+        let node = NodeValue {
+            value: total / empty as f64,
+            pruned,
+        };
+        visitor.store(&board, depth, probability, node);
+        Some(node)
+    }
+}
+
+impl Default for Expectimax<Weighted> {
+    /// Create a search using [`Weighted::standard`] as evaluation
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::Expectimax;
+    /// use game_2048_model::models::{Model, ArrayModel};
+    ///
+    /// let search = Expectimax::default();
+    ///
+    /// assert_eq!(search.best_direction(&ArrayModel::new()), None);
+    /// ```
+    ///
+    fn default() -> Self {
+        Expectimax::new(Weighted::standard())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArrayBoard, Directions, Expectimax};
+    use crate::models::{ArrayModel, Matrix};
+
+    fn empty_squares(board: &ArrayBoard) -> f64 {
+        board.iter().filter(|x| **x == 0).count() as f64
+    }
+
+    mod best_direction {
+        use super::{empty_squares, ArrayBoard, ArrayModel, Directions, Expectimax, Matrix};
+
+        #[rustfmt::skip]
+        #[test]
+        fn returns_none_without_legal_moves() {
+            let game = ArrayModel::from([
+                1,2,1,2,
+                2,1,2,1,
+                1,2,1,2,
+                2,1,2,1
+            ]);
+
+            assert_eq!(Expectimax::new(empty_squares).best_direction(&game), None);
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn returns_the_only_legal_move() {
+            let game = ArrayModel::from([
+                0,2,1,2,
+                0,1,2,1,
+                0,2,1,2,
+                0,1,2,1
+            ]);
+
+            assert_eq!(Expectimax::new(empty_squares).best_direction(&game), Some(Directions::Left));
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn prefers_merging() {
+            let game = ArrayModel::from([
+                1,1,0,0,
+                0,0,0,0,
+                0,0,0,0,
+                0,0,0,0
+            ]);
+
+            let direction = Expectimax::new(empty_squares).depth(1).best_direction(&game);
+
+            assert!(direction == Some(Directions::Left) || direction == Some(Directions::Right));
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn same_result_for_all_models() {
+            let board = [
+                1,0,2,0,
+                3,1,0,0,
+                0,0,1,0,
+                4,3,2,1
+            ];
+            let search = Expectimax::new(empty_squares);
+
+            assert_eq!(
+                search.best_direction(&ArrayModel::from(board)),
+                search.best_direction(&Matrix::from(board))
+            );
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn never_prefers_ending_the_game() {
+            // Every spawn after sliding left leaves a board without legal moves
+            let game = ArrayModel::from([
+                4,2,0,6,
+                1,4,5,3,
+                6,5,4,5,
+                2,1,6,1
+            ]);
+            let search = Expectimax::new(|_: &ArrayBoard| -1.0).depth(2);
+
+            assert_ne!(search.best_direction(&game), Some(Directions::Left));
+            assert_eq!(search.best_direction(&game), Some(Directions::Up));
+        }
+    }
+
+    mod evaluate {
+        use super::{empty_squares, ArrayBoard, ArrayModel, Expectimax};
+
+        #[rustfmt::skip]
+        #[test]
+        fn illegal_directions_are_none() {
+            let game = ArrayModel::from([
+                0,2,1,2,
+                0,1,2,1,
+                0,2,1,2,
+                0,1,2,1
+            ]);
+
+            let values = Expectimax::new(empty_squares).depth(1).evaluate(&game);
+
+            assert_eq!(values, [None, None, None, Some(4.0)]);
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn cutoff_evaluates_chance_nodes() {
+            let game = ArrayModel::from([
+                0,2,1,2,
+                0,1,2,1,
+                0,2,1,2,
+                0,1,2,1
+            ]);
+
+            let values = Expectimax::new(empty_squares)
+                .depth(3)
+                .probability_cutoff(2.0)
+                .evaluate(&game);
+
+            assert_eq!(values, [None, None, None, Some(4.0)]);
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn deeper_search_averages_over_spawns() {
+            let game = ArrayModel::from([
+                0,2,1,2,
+                0,1,2,1,
+                0,2,1,2,
+                0,1,2,1
+            ]);
+
+            let values = Expectimax::new(empty_squares).depth(2).evaluate(&game);
+
+            // Every spawn leaves three empty squares in the right column and
+            // the best reply never does better than keeping them.
+            assert!(values[3].unwrap() >= 3.0);
+            assert!(values[3].unwrap() < 4.0);
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn game_over_is_worth_less_than_any_board_in_play() {
+            let game = ArrayModel::from([
+                4,2,0,6,
+                1,4,5,3,
+                6,5,4,5,
+                2,1,6,1
+            ]);
+
+            let values = Expectimax::new(|_: &ArrayBoard| -1.0).depth(2).evaluate(&game);
+
+            assert!(values[3].unwrap() < -1.0e8);
+            assert!(values[0].unwrap() > -1.0e8);
+        }
+    }
+}
@@ -0,0 +1,676 @@
+//! Board evaluation functions used by the agents
+//!
+//! Every evaluation implements [`Heuristic`] and can be combined with
+//! [`Weighted`] to tune how much each term counts. Closures taking an
+//! [`ArrayBoard`] are heuristics as well.
+//!
+//! ```
+//! use game_2048_model::ai::heuristics::*;
+//!
+//! let heuristic = Weighted::new()
+//!     .add(2.0, EmptySquares)
+//!     .add(1.0, Monotonicity);
+//!
+//! let board = [
+//!     3,2,1,0,
+//!     2,1,0,0,
+//!     1,0,0,0,
+//!     0,0,0,0
+//! ];
+//!
+//! assert_eq!(heuristic.score(&board), 20.0);
+//! ```
+
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use crate::base::*;
+
+/// A score of how good a board is, higher is better
+pub trait Heuristic {
+    /// Score the board given in array form
+    fn score(&self, board: &ArrayBoard) -> f64;
+
+    /// Score the board of a game model
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::heuristics::{EmptySquares, Heuristic};
+    /// use game_2048_model::models::{Model, Matrix};
+    ///
+    /// assert_eq!(EmptySquares.score_model(&Matrix::new()), 16.0);
+    /// ```
+    ///
+    fn score_model<M: Model>(&self, game: &M) -> f64
+    where
+        Self: Sized,
+    {
+        self.score(&game.as_array())
+    }
+
+    /// A short name of the heuristic, used when explaining scores
+    ///
+    /// Closures and heuristics that do not give a name are called `custom`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::heuristics::{EmptySquares, Heuristic};
+    /// use game_2048_model::ArrayBoard;
+    ///
+    /// assert_eq!(EmptySquares.name(), "empty squares");
+    /// assert_eq!((|_: &ArrayBoard| 0.0).name(), "custom");
+    /// ```
+    ///
+    fn name(&self) -> &str {
+        "custom"
+    }
+}
+
+impl<F> Heuristic for F
+where
+    F: Fn(&ArrayBoard) -> f64,
+{
+    fn score(&self, board: &ArrayBoard) -> f64 {
+        self(board)
+    }
+}
+
+impl Heuristic for Box<dyn Heuristic + Send + Sync> {
+    fn score(&self, board: &ArrayBoard) -> f64 {
+        self.as_ref().score(board)
+    }
+
+    fn name(&self) -> &str {
+        self.as_ref().name()
+    }
+}
+
+/// The value of the exponent at the given row and column
+fn at(board: &ArrayBoard, row: usize, col: usize) -> BoardElement {
+    board[row * BOARD_SIZE + col]
+}
+
+/// All rows and columns of the board as lines of exponents
+///
+/// Rows are read from left to right and columns from top to bottom.
+fn lines(board: &ArrayBoard) -> [[BoardElement; BOARD_SIZE]; 2 * BOARD_SIZE] {
+    let mut lines = [[0; BOARD_SIZE]; 2 * BOARD_SIZE];
+    for (i, square) in board.iter().enumerate() {
+        let (row, col) = (i / BOARD_SIZE, i % BOARD_SIZE);
+        lines[row][col] = *square;
+        lines[BOARD_SIZE + col][row] = *square;
+    }
+    lines
+}
+
+/// Counts the empty squares
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::heuristics::{EmptySquares, Heuristic};
+///
+/// let board = [
+///     1,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,2
+/// ];
+///
+/// assert_eq!(EmptySquares.score(&board), 14.0);
+/// ```
+///
+#[derive(Debug, Copy, Clone, Default)]
+pub struct EmptySquares;
+
+impl Heuristic for EmptySquares {
+    fn score(&self, board: &ArrayBoard) -> f64 {
+        board.iter().filter(|x| **x == 0).count() as f64
+    }
+
+    fn name(&self) -> &str {
+        "empty squares"
+    }
+}
+
+/// Penalises rows and columns that are not sorted
+///
+/// For every line the exponent steps going against the line's best direction
+/// are summed and subtracted, so a board where every row and column is
+/// monotonic scores 0.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::heuristics::{Heuristic, Monotonicity};
+///
+/// let board = [
+///     1,3,2,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0
+/// ];
+///
+/// // The first row goes up by 2 and down by 3, every column only goes down.
+/// assert_eq!(Monotonicity.score(&board), -2.0);
+/// ```
+///
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Monotonicity;
+
+impl Heuristic for Monotonicity {
+    fn score(&self, board: &ArrayBoard) -> f64 {
+        let mut total = 0.0;
+        for line in lines(board).iter() {
+            let mut increasing = 0.0;
+            let mut decreasing = 0.0;
+            for pair in line.windows(2) {
+                let step = f64::from(pair[1]) - f64::from(pair[0]);
+                if step > 0.0 {
+                    increasing += step;
+                } else {
+                    decreasing -= step;
+                }
+            }
+            total -= f64::min(increasing, decreasing);
+        }
+        total
+    }
+
+    fn name(&self) -> &str {
+        "monotonicity"
+    }
+}
+
+/// Penalises differences between neighbouring numbers
+///
+/// The absolute exponent difference of every pair of horizontally or
+/// vertically adjacent non-empty squares is subtracted.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::heuristics::{Heuristic, Smoothness};
+///
+/// let board = [
+///     1,3,0,0,
+///     2,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0
+/// ];
+///
+/// assert_eq!(Smoothness.score(&board), -3.0);
+/// ```
+///
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Smoothness;
+
+impl Heuristic for Smoothness {
+    fn score(&self, board: &ArrayBoard) -> f64 {
+        let mut total = 0.0;
+        for line in lines(board).iter() {
+            for pair in line.windows(2) {
+                if pair[0] != 0 && pair[1] != 0 {
+                    total -= (f64::from(pair[0]) - f64::from(pair[1])).abs();
+                }
+            }
+        }
+        total
+    }
+
+    fn name(&self) -> &str {
+        "smoothness"
+    }
+}
+
+/// Scores 1 when the largest number is in one of the corners, otherwise 0
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::heuristics::{Heuristic, MaxInCorner};
+///
+/// let board = [
+///     1,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,5
+/// ];
+///
+/// assert_eq!(MaxInCorner.score(&board), 1.0);
+/// ```
+///
+#[derive(Debug, Copy, Clone, Default)]
+pub struct MaxInCorner;
+
+impl Heuristic for MaxInCorner {
+    fn score(&self, board: &ArrayBoard) -> f64 {
+        let max = board.iter().max().copied().unwrap_or(0);
+        let last = BOARD_SIZE - 1;
+        let corners = [
+            at(board, 0, 0),
+            at(board, 0, last),
+            at(board, last, 0),
+            at(board, last, last),
+        ];
+        if max != 0 && corners.contains(&max) {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    fn name(&self) -> &str {
+        "max in corner"
+    }
+}
+
+/// Counts the pairs of equal neighbouring numbers that can be merged
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::heuristics::{Heuristic, MergePotential};
+///
+/// let board = [
+///     1,1,1,0,
+///     2,0,0,0,
+///     2,0,0,0,
+///     0,0,0,0
+/// ];
+///
+/// assert_eq!(MergePotential.score(&board), 3.0);
+/// ```
+///
+#[derive(Debug, Copy, Clone, Default)]
+pub struct MergePotential;
+
+impl Heuristic for MergePotential {
+    fn score(&self, board: &ArrayBoard) -> f64 {
+        let mut total = 0.0;
+        for line in lines(board).iter() {
+            for pair in line.windows(2) {
+                if pair[0] != 0 && pair[0] == pair[1] {
+                    total += 1.0;
+                }
+            }
+        }
+        total
+    }
+
+    fn name(&self) -> &str {
+        "merge potential"
+    }
+}
+
+#[rustfmt::skip]
+const SNAKE_WEIGHTS: [f64; BOARD_SIZE * BOARD_SIZE] = [
+    32768.0, 16384.0, 8192.0, 4096.0,
+    256.0, 512.0, 1024.0, 2048.0,
+    128.0, 64.0, 32.0, 16.0,
+    1.0, 2.0, 4.0, 8.0
+];
+
+/// Sum of the numbers on the board multiplied by a weight for each square
+///
+/// The default weights follow a snake starting in the top left corner,
+/// rewarding boards where the numbers are ordered along the snake.
+/// The score is scaled down by the largest weight.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::heuristics::{Heuristic, SnakePattern};
+///
+/// let board = [
+///     2,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0
+/// ];
+///
+/// assert_eq!(SnakePattern::default().score(&board), 4.0);
+/// ```
+///
+#[derive(Debug, Copy, Clone)]
+pub struct SnakePattern {
+    weights: [f64; BOARD_SIZE * BOARD_SIZE],
+}
+
+impl SnakePattern {
+    /// Create a pattern with custom weights given in array form
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::heuristics::{Heuristic, SnakePattern};
+    ///
+    /// let pattern = SnakePattern::new([
+    ///     1.0,0.0,0.0,0.0,
+    ///     0.0,0.0,0.0,0.0,
+    ///     0.0,0.0,0.0,0.0,
+    ///     0.0,0.0,0.0,0.0
+    /// ]);
+    ///
+    /// assert_eq!(pattern.score(&[3,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]), 8.0);
+    /// ```
+    ///
+    pub fn new(weights: [f64; BOARD_SIZE * BOARD_SIZE]) -> Self {
+        SnakePattern { weights }
+    }
+}
+
+impl Default for SnakePattern {
+    fn default() -> Self {
+        SnakePattern::new(SNAKE_WEIGHTS)
+    }
+}
+
+impl Heuristic for SnakePattern {
+    fn score(&self, board: &ArrayBoard) -> f64 {
+        let max_weight = self.weights.iter().cloned().fold(0.0, f64::max);
+        if max_weight == 0.0 {
+            return 0.0;
+        }
+        let mut total = 0.0;
+        for (exponent, weight) in board.iter().zip(self.weights.iter()) {
+            if *exponent != 0 {
+                total += weight * 2f64.powi(i32::from(*exponent));
+            }
+        }
+        total / max_weight
+    }
+
+    fn name(&self) -> &str {
+        "snake pattern"
+    }
+}
+
+/// Weighted sum of other heuristics
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::heuristics::*;
+///
+/// let heuristic = Weighted::new()
+///     .add(1.0, EmptySquares)
+///     .add(10.0, MaxInCorner);
+///
+/// let board = [
+///     2,1,0,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0
+/// ];
+///
+/// assert_eq!(heuristic.score(&board), 24.0);
+/// ```
+///
+#[derive(Default)]
+pub struct Weighted {
+    terms: Vec<(f64, Box<dyn Heuristic + Send + Sync>)>,
+}
+
+impl Weighted {
+    /// Create a combination without any terms, scoring every board as 0
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::heuristics::{Heuristic, Weighted};
+    ///
+    /// assert_eq!(Weighted::new().score(&[0; 16]), 0.0);
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Weighted { terms: Vec::new() }
+    }
+
+    /// Create the combination used by default by the agents
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::heuristics::{Heuristic, Weighted};
+    ///
+    /// let heuristic = Weighted::standard();
+    /// let empty = [0; 16];
+    /// let mut full = [1; 16];
+    /// full[0] = 2;
+    ///
+    /// assert!(heuristic.score(&empty) > heuristic.score(&full));
+    /// ```
+    ///
+    pub fn standard() -> Self {
+        Weighted::new()
+            .add(2.7, EmptySquares)
+            .add(1.0, Monotonicity)
+            .add(0.1, Smoothness)
+            .add(0.7, MergePotential)
+            .add(1.0, MaxInCorner)
+    }
+
+    /// Add a heuristic with the given weight
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::heuristics::{EmptySquares, Weighted};
+    ///
+    /// let heuristic = Weighted::new().add(0.5, EmptySquares);
+    /// ```
+    ///
+    pub fn add<H>(mut self, weight: f64, heuristic: H) -> Self
+    where
+        H: Heuristic + Send + Sync + 'static,
+    {
+        self.terms.push((weight, Box::new(heuristic)));
+        self
+    }
+
+    /// The weighted score of every term in the order they were added
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::heuristics::{EmptySquares, MaxInCorner, Weighted};
+    ///
+    /// let heuristic = Weighted::new()
+    ///     .add(1.0, EmptySquares)
+    ///     .add(10.0, MaxInCorner);
+    ///
+    /// assert_eq!(heuristic.terms(&[0; 16]), vec![16.0, 0.0]);
+    /// ```
+    ///
+    pub fn terms(&self, board: &ArrayBoard) -> Vec<f64> {
+        self.terms
+            .iter()
+            .map(|(weight, heuristic)| weight * heuristic.score(board))
+            .collect()
+    }
+
+    /// The name of every term in the order they were added
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::heuristics::{EmptySquares, MaxInCorner, Weighted};
+    ///
+    /// let heuristic = Weighted::new()
+    ///     .add(1.0, EmptySquares)
+    ///     .add(10.0, MaxInCorner);
+    ///
+    /// assert_eq!(heuristic.names(), vec!["empty squares", "max in corner"]);
+    /// ```
+    ///
+    pub fn names(&self) -> Vec<&str> {
+        self.terms
+            .iter()
+            .map(|(_, heuristic)| heuristic.name())
+            .collect()
+    }
+}
+
+impl Heuristic for Weighted {
+    fn score(&self, board: &ArrayBoard) -> f64 {
+        self.terms
+            .iter()
+            .map(|(weight, heuristic)| weight * heuristic.score(board))
+            .sum()
+    }
+
+    fn name(&self) -> &str {
+        "weighted"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod monotonicity {
+        use super::*;
+
+        #[rustfmt::skip]
+        #[test]
+        fn sorted_board_scores_zero() {
+            let board = [
+                4,3,2,1,
+                3,2,1,0,
+                2,1,0,0,
+                1,0,0,0
+            ];
+
+            assert_eq!(Monotonicity.score(&board), 0.0);
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn same_score_when_mirrored() {
+            let board = [
+                1,3,2,0,
+                0,2,0,1,
+                0,0,5,0,
+                0,1,0,0
+            ];
+            let mut mirrored = board;
+            for row in 0..4 {
+                for col in 0..4 {
+                    mirrored[row * 4 + col] = board[row * 4 + 3 - col];
+                }
+            }
+
+            assert_eq!(Monotonicity.score(&board), Monotonicity.score(&mirrored));
+        }
+    }
+
+    mod smoothness {
+        use super::*;
+
+        #[rustfmt::skip]
+        #[test]
+        fn ignores_empty_squares() {
+            let board = [
+                1,0,5,0,
+                0,0,0,0,
+                7,0,0,0,
+                0,0,0,0
+            ];
+
+            assert_eq!(Smoothness.score(&board), 0.0);
+        }
+    }
+
+    mod max_in_corner {
+        use super::*;
+
+        #[rustfmt::skip]
+        #[test]
+        fn scores_zero_outside_corner() {
+            let board = [
+                1,0,0,0,
+                0,3,0,0,
+                0,0,0,0,
+                0,0,0,2
+            ];
+
+            assert_eq!(MaxInCorner.score(&board), 0.0);
+        }
+
+        #[test]
+        fn scores_zero_on_empty_board() {
+            assert_eq!(MaxInCorner.score(&[0; 16]), 0.0);
+        }
+    }
+
+    mod merge_potential {
+        use super::*;
+
+        #[rustfmt::skip]
+        #[test]
+        fn ignores_empty_squares() {
+            let board = [
+                0,0,0,0,
+                0,0,0,0,
+                0,0,0,0,
+                0,0,0,0
+            ];
+
+            assert_eq!(MergePotential.score(&board), 0.0);
+        }
+    }
+
+    mod snake_pattern {
+        use super::*;
+
+        #[rustfmt::skip]
+        #[test]
+        fn prefers_numbers_along_the_snake() {
+            let along = [
+                3,2,0,0,
+                0,0,0,0,
+                0,0,0,0,
+                0,0,0,0
+            ];
+            let against = [
+                0,0,0,0,
+                0,0,0,0,
+                0,0,0,0,
+                3,2,0,0
+            ];
+
+            let pattern = SnakePattern::default();
+
+            assert!(pattern.score(&along) > pattern.score(&against));
+        }
+
+        #[test]
+        fn zero_weights_score_zero() {
+            assert_eq!(SnakePattern::new([0.0; 16]).score(&[1; 16]), 0.0);
+        }
+    }
+
+    mod weighted {
+        use super::*;
+
+        #[test]
+        fn accepts_closures() {
+            let heuristic = Weighted::new().add(2.0, |_: &ArrayBoard| 1.5);
+
+            assert_eq!(heuristic.score(&[0; 16]), 3.0);
+        }
+
+        #[test]
+        fn score_is_sum_of_terms() {
+            let heuristic = Weighted::standard();
+            let board = [1, 2, 0, 0, 3, 0, 0, 0, 0, 1, 0, 0, 2, 0, 0, 0];
+
+            let sum: f64 = heuristic.terms(&board).iter().sum();
+
+            assert_eq!(heuristic.score(&board), sum);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use super::heuristics::Weighted;
+use super::Expectimax;
+use crate::base::*;
+
+/// One heuristic term of the board after a move
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    /// The name of the heuristic, see [`Heuristic::name`](super::heuristics::Heuristic::name)
+    pub name: String,
+    /// The weighted score of the term on the board after the move
+    pub value: f64,
+    /// How much higher the term is than its average over the legal moves
+    pub relative: f64,
+}
+
+/// A direction together with why it is or is not a good move
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hint {
+    /// The direction this hint is about
+    pub direction: Directions,
+    /// Whether the direction changes the board
+    pub legal: bool,
+    /// The expected value found by the search, `None` if the move is not legal
+    pub expected_value: Option<f64>,
+    /// The score gained by the merges of the move
+    pub score: u64,
+    /// The heuristic terms of the board right after the move, empty if the move is not legal
+    pub terms: Vec<Term>,
+}
+
+impl Hint {
+    /// The terms ordered from the one favouring this move the most to the one
+    /// favouring it the least compared to the other legal moves
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::hint;
+    /// use game_2048_model::models::ArrayModel;
+    ///
+    /// let game = ArrayModel::from([
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     1,0,0,0,
+    ///     3,3,1,0
+    /// ]);
+    /// let best = &hint(&game)[0];
+    ///
+    /// println!("{:?} because of {}", best.direction, best.reasons()[0].name);
+    /// ```
+    ///
+    pub fn reasons(&self) -> Vec<&Term> {
+        let mut reasons: Vec<&Term> = self.terms.iter().collect();
+        reasons.sort_by(|a, b| {
+            b.relative
+                .partial_cmp(&a.relative)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        reasons
+    }
+}
+
+/// All four directions ranked from best to worst with the default search
+///
+/// Uses [`Expectimax::default`], see [`hint_with`].
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::hint;
+/// use game_2048_model::models::{Directions, Matrix};
+///
+/// let game = Matrix::from([
+///     [0, 0, 0, 0],
+///     [4, 0, 0, 2],
+///     [2, 8, 4, 0],
+///     [2, 8, 4, 16]
+/// ]);
+/// let hints = hint(&game);
+///
+/// assert_eq!(hints.len(), 4);
+/// assert!(hints[0].legal);
+/// ```
+///
+pub fn hint<M: Model>(game: &M) -> Vec<Hint> {
+    hint_with(game, &Expectimax::default())
+}
+
+/// All four directions ranked from best to worst
+///
+/// Legal moves come first, ordered by the expected value found by the search
+/// with ties broken in the order of [`DIRECTIONS`], followed by the moves that
+/// do not change the board. Every legal move lists the terms of the search's
+/// evaluation on the board right after the move.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::heuristics::{EmptySquares, MaxInCorner, Weighted};
+/// use game_2048_model::ai::{hint_with, Expectimax};
+/// use game_2048_model::models::{ArrayModel, Directions};
+///
+/// let game = ArrayModel::from([
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     1,1,0,0
+/// ]);
+/// let search = Expectimax::new(Weighted::new().add(1.0, EmptySquares).add(1.0, MaxInCorner)).depth(1);
+/// let hints = hint_with(&game, &search);
+///
+/// // Merging left and right are equally good, ties go in the order of DIRECTIONS
+/// assert_eq!(hints[0].direction, Directions::Right);
+/// assert_eq!(hints[0].score, 4);
+/// assert_eq!(hints[0].terms[0].name, "empty squares");
+/// assert_eq!(hints[3].direction, Directions::Down);
+/// assert!(!hints[3].legal);
+/// ```
+///
+pub fn hint_with<M: Model>(game: &M, search: &Expectimax<Weighted>) -> Vec<Hint> {
+    let board = game.as_array();
+    let values = search.evaluate(game);
+    let names = search.evaluation.names();
+
+    let mut hints: Vec<Hint> = DIRECTIONS
+        .iter()
+        .zip(values.iter())
+        .map(|(direction, value)| {
+            let mut child = M::from(board);
+            match value {
+                Some(_) if child.slide(*direction).is_some() => {
+                    let after = child.as_array();
+                    Hint {
+                        direction: *direction,
+                        legal: true,
+                        expected_value: *value,
+                        score: slide_score(&board, &after),
+                        terms: names
+                            .iter()
+                            .zip(search.evaluation.terms(&after))
+                            .map(|(name, value)| Term {
+                                name: name.to_string(),
+                                value,
+                                relative: 0.0,
+                            })
+                            .collect(),
+                    }
+                }
+                _ => Hint {
+                    direction: *direction,
+                    legal: false,
+                    expected_value: None,
+                    score: 0,
+                    terms: Vec::new(),
+                },
+            }
+        })
+        .collect();
+
+    let legal = hints.iter().filter(|hint| hint.legal).count();
+    for term in 0..names.len() {
+        let total: f64 = hints
+            .iter()
+            .filter(|hint| hint.legal)
+            .map(|hint| hint.terms[term].value)
+            .sum();
+        let average = total / legal.max(1) as f64;
+        for hint in hints.iter_mut().filter(|hint| hint.legal) {
+            hint.terms[term].relative = hint.terms[term].value - average;
+        }
+    }
+
+    // The sort is stable, so ties keep the order of DIRECTIONS
+    hints.sort_by(|a, b| match (a.expected_value, b.expected_value) {
+        (Some(a), Some(b)) => b.partial_cmp(&a).unwrap_or(std::cmp::Ordering::Equal),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+    hints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hint, hint_with};
+    use crate::ai::heuristics::{EmptySquares, Monotonicity, Weighted};
+    use crate::ai::Expectimax;
+    use crate::models::{ArrayModel, Directions, Matrix};
+    use std::collections::HashSet;
+
+    #[rustfmt::skip]
+    const BOARD: [u8; 16] = [
+        1,0,2,0,
+        3,1,0,0,
+        0,0,1,0,
+        4,3,2,1
+    ];
+
+    mod hint {
+        use super::*;
+
+        #[test]
+        fn first_hint_is_the_best_direction() {
+            let game = ArrayModel::from(BOARD);
+
+            assert_eq!(
+                Some(hint(&game)[0].direction),
+                Expectimax::default().best_direction(&game)
+            );
+        }
+
+        #[test]
+        fn covers_every_direction_once() {
+            let directions: HashSet<Directions> = hint(&Matrix::from(BOARD))
+                .iter()
+                .map(|hint| hint.direction)
+                .collect();
+
+            assert_eq!(directions.len(), 4);
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn dead_board_has_no_legal_hints() {
+            let game = ArrayModel::from([
+                1,2,1,2,
+                2,1,2,1,
+                1,2,1,2,
+                2,1,2,1
+            ]);
+            let hints = hint(&game);
+
+            assert!(hints.iter().all(|hint| !hint.legal && hint.terms.is_empty()));
+        }
+    }
+
+    mod hint_with {
+        use super::*;
+
+        #[test]
+        fn relative_terms_average_to_zero() {
+            let search = Expectimax::new(
+                Weighted::new()
+                    .add(1.0, EmptySquares)
+                    .add(0.5, Monotonicity),
+            )
+            .depth(1);
+            let hints = hint_with(&ArrayModel::from(BOARD), &search);
+
+            for term in 0..2 {
+                let total: f64 = hints
+                    .iter()
+                    .filter(|hint| hint.legal)
+                    .map(|hint| hint.terms[term].relative)
+                    .sum();
+                assert!(total.abs() < 1e-9);
+            }
+        }
+
+        #[test]
+        fn reasons_start_with_the_largest_relative_term() {
+            let search = Expectimax::new(
+                Weighted::new()
+                    .add(1.0, EmptySquares)
+                    .add(0.5, Monotonicity),
+            )
+            .depth(1);
+            let hints = hint_with(&ArrayModel::from(BOARD), &search);
+
+            for hint in hints.iter().filter(|hint| hint.legal) {
+                let reasons = hint.reasons();
+                assert!(reasons[0].relative >= reasons[1].relative);
+            }
+        }
+    }
+}
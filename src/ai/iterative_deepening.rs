@@ -0,0 +1,342 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use std::time::{Duration, Instant};
+
+use super::expectimax::{best_of, Visitor};
+use super::heuristics::Heuristic;
+use super::Expectimax;
+use crate::base::*;
+
+/// How often the clock is read, in visited nodes
+const CLOCK_INTERVAL: u64 = 256;
+
+/// How long [`IterativeDeepening`] searches before answering
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SearchBudget {
+    /// Stop once the time has passed
+    Time(Duration),
+    /// Stop once this many nodes have been visited, reproducible on any machine
+    Nodes(u64),
+}
+
+/// The outcome of an [`IterativeDeepening`] search
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Deepening {
+    /// The best direction of the deepest completed iteration
+    pub direction: Option<Directions>,
+    /// The expected value of each direction in the order of [`DIRECTIONS`]
+    pub values: [Option<f64>; 4],
+    /// The depth of the deepest completed iteration
+    pub depth: usize,
+    /// The number of nodes visited over all iterations, including the unfinished one
+    pub nodes: u64,
+}
+
+/// Expectimax search at increasing depths until a budget runs out
+///
+/// Searches to depth 1, then 2 and so on up to the depth of the wrapped
+/// [`Expectimax`], and answers with the deepest iteration that finished
+/// within the budget. The first iteration always finishes, so there is an
+/// answer even with a budget of zero. The values of a finished iteration are
+/// the same as those of [`Expectimax::evaluate`] at that depth.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::{Expectimax, IterativeDeepening, SearchBudget};
+/// use game_2048_model::models::Matrix;
+/// use std::time::Duration;
+///
+/// let game = Matrix::from([
+///     [0, 0, 0, 0],
+///     [4, 0, 0, 2],
+///     [2, 8, 4, 0],
+///     [3, 8, 6, 4]
+/// ]);
+/// let search = IterativeDeepening::new(
+///     Expectimax::default().depth(10),
+///     SearchBudget::Time(Duration::from_millis(50)),
+/// );
+///
+/// let result = search.search(&game);
+/// assert!(result.direction.is_some());
+/// assert!(result.depth >= 1);
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct IterativeDeepening<E> {
+    search: Expectimax<E>,
+    budget: SearchBudget,
+}
+
+/// The budget of a single iteration, stopping the recursion of [`Expectimax`]
+struct Iteration {
+    nodes: u64,
+    node_limit: Option<u64>,
+    deadline: Option<Instant>,
+    depth_limited: bool,
+}
+
+impl<E> IterativeDeepening<E>
+where
+    E: Heuristic,
+{
+    /// Create a search going at most as deep as the given search
+    ///
+    /// The evaluation function and probability cutoff of `search` are used at
+    /// every depth.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Expectimax, IterativeDeepening, SearchBudget};
+    ///
+    /// let search = IterativeDeepening::new(
+    ///     Expectimax::default().depth(8),
+    ///     SearchBudget::Nodes(100_000),
+    /// );
+    /// ```
+    ///
+    pub fn new(search: Expectimax<E>, budget: SearchBudget) -> Self {
+        IterativeDeepening { search, budget }
+    }
+
+    /// Returns the best direction to play, or `None` if no direction changes the board
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Expectimax, IterativeDeepening, SearchBudget};
+    /// use game_2048_model::models::{Directions, Matrix};
+    /// use game_2048_model::ArrayBoard;
+    ///
+    /// let game = Matrix::from([
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     1,2,3,4
+    /// ]);
+    /// let search = IterativeDeepening::new(
+    ///     Expectimax::new(|board: &ArrayBoard| board.iter().filter(|x| **x == 0).count() as f64),
+    ///     SearchBudget::Nodes(10_000),
+    /// );
+    ///
+    /// assert_eq!(search.best_direction(&game), Some(Directions::Up));
+    /// ```
+    ///
+    pub fn best_direction<M: Model>(&self, game: &M) -> Option<Directions> {
+        self.search(game).direction
+    }
+
+    /// Search at increasing depths and return the deepest finished iteration
+    ///
+    /// Stops early once an iteration is not limited by its depth, since
+    /// deeper iterations would give the same values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Expectimax, IterativeDeepening, SearchBudget};
+    /// use game_2048_model::models::Matrix;
+    ///
+    /// let game = Matrix::from([
+    ///     [0, 0, 0, 0],
+    ///     [4, 0, 0, 2],
+    ///     [2, 8, 4, 0],
+    ///     [3, 8, 6, 4]
+    /// ]);
+    /// let search = IterativeDeepening::new(Expectimax::default().depth(2), SearchBudget::Nodes(u64::MAX));
+    ///
+    /// let result = search.search(&game);
+    /// assert_eq!(result.depth, 2);
+    /// assert_eq!(result.values, Expectimax::default().depth(2).evaluate(&game));
+    /// ```
+    ///
+    pub fn search<M: Model>(&self, game: &M) -> Deepening {
+        let (node_limit, deadline) = match self.budget {
+            SearchBudget::Time(duration) => (None, Some(Instant::now() + duration)),
+            SearchBudget::Nodes(nodes) => (Some(nodes), None),
+        };
+        let board = game.as_array();
+        let mut result = Deepening {
+            direction: None,
+            values: [None; 4],
+            depth: 0,
+            nodes: 0,
+        };
+
+        for depth in 1..=self.search.depth {
+            // The first iteration always finishes so there is a direction to answer with
+            let mut iteration = Iteration {
+                nodes: result.nodes,
+                node_limit: node_limit.filter(|_| depth > 1),
+                deadline: deadline.filter(|_| depth > 1),
+                depth_limited: false,
+            };
+            let values = self.search.root::<M, _>(&mut iteration, board, depth);
+            result.nodes = iteration.nodes;
+            let values = match values {
+                Some(values) => values,
+                None => break,
+            };
+
+            result.direction = best_of(&values);
+            result.values = values;
+            result.depth = depth;
+            if !iteration.depth_limited || iteration.out_of_time() {
+                break;
+            }
+        }
+        result
+    }
+}
+
+impl Iteration {
+    fn out_of_time(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+impl Visitor for Iteration {
+    /// Count a visited node, returns `None` once the budget is spent
+    fn visit(&mut self) -> Option<()> {
+        self.nodes += 1;
+        if self.node_limit.is_some_and(|limit| self.nodes > limit) {
+            return None;
+        }
+        if self.nodes.is_multiple_of(CLOCK_INTERVAL) && self.out_of_time() {
+            return None;
+        }
+        Some(())
+    }
+
+    fn depth_limited(&mut self) {
+        self.depth_limited = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expectimax, IterativeDeepening, SearchBudget};
+    use crate::models::{ArrayModel, Directions, Matrix};
+    use crate::ArrayBoard;
+    use std::time::{Duration, Instant};
+
+    #[rustfmt::skip]
+    const BOARD: [u8; 16] = [
+        1,0,2,0,
+        3,1,0,0,
+        0,0,1,0,
+        4,3,2,1
+    ];
+
+    mod search {
+        use super::*;
+
+        #[test]
+        fn finished_iterations_match_fixed_depth() {
+            let game = ArrayModel::from(BOARD);
+            for depth in 1..=3 {
+                let search = IterativeDeepening::new(
+                    Expectimax::default().depth(depth),
+                    SearchBudget::Nodes(u64::MAX),
+                );
+                let result = search.search(&game);
+
+                assert_eq!(result.depth, depth);
+                assert_eq!(
+                    result.values,
+                    Expectimax::default().depth(depth).evaluate(&game)
+                );
+            }
+        }
+
+        #[test]
+        fn node_budget_stops_at_a_shallower_depth() {
+            let game = Matrix::from(BOARD);
+            let unlimited = IterativeDeepening::new(
+                Expectimax::default().depth(3),
+                SearchBudget::Nodes(u64::MAX),
+            )
+            .search(&game);
+            let limited = IterativeDeepening::new(
+                Expectimax::default().depth(3),
+                SearchBudget::Nodes(unlimited.nodes - 1),
+            )
+            .search(&game);
+
+            assert_eq!(limited.depth, 2);
+            assert_eq!(
+                limited.values,
+                Expectimax::default().depth(2).evaluate(&game)
+            );
+        }
+
+        #[test]
+        fn zero_budget_still_answers() {
+            let search =
+                IterativeDeepening::new(Expectimax::default().depth(5), SearchBudget::Nodes(0));
+            let result = search.search(&ArrayModel::from(BOARD));
+
+            assert_eq!(result.depth, 1);
+            assert!(result.direction.is_some());
+        }
+
+        #[test]
+        fn answers_within_the_time_budget() {
+            let search = IterativeDeepening::new(
+                Expectimax::default().depth(20),
+                SearchBudget::Time(Duration::from_millis(50)),
+            );
+            let start = Instant::now();
+            let result = search.search(&ArrayModel::from(BOARD));
+
+            assert!(start.elapsed() < Duration::from_millis(500));
+            assert!(result.depth < 20);
+        }
+
+        #[test]
+        fn stops_when_depth_does_not_matter() {
+            // Every chance node below the root is cut off from depth 2 on
+            let search = Expectimax::default().depth(10).probability_cutoff(2.0);
+            let result = IterativeDeepening::new(search, SearchBudget::Nodes(u64::MAX))
+                .search(&ArrayModel::from(BOARD));
+
+            assert_eq!(result.depth, 2);
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn never_prefers_ending_the_game() {
+            // Every spawn after sliding left leaves a board without legal moves
+            let game = ArrayModel::from([
+                4,2,0,6,
+                1,4,5,3,
+                6,5,4,5,
+                2,1,6,1
+            ]);
+            let search = Expectimax::new(|_: &ArrayBoard| -1.0).depth(2);
+            let result = IterativeDeepening::new(search, SearchBudget::Nodes(u64::MAX)).search(&game);
+
+            assert_eq!(result.depth, 2);
+            assert_ne!(result.direction, Some(Directions::Left));
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn returns_none_without_legal_moves() {
+            let game = ArrayModel::from([
+                1,2,1,2,
+                2,1,2,1,
+                1,2,1,2,
+                2,1,2,1
+            ]);
+            let result = IterativeDeepening::new(Expectimax::default(), SearchBudget::Nodes(100))
+                .search(&game);
+
+            assert_eq!(result.direction, None);
+        }
+    }
+}
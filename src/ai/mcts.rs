@@ -0,0 +1,527 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use std::time::{Duration, Instant};
+
+use rand::prelude::*;
+
+use crate::base::*;
+
+/// How long [`Mcts`] searches before choosing a direction
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Budget {
+    /// Run a fixed number of iterations, reproducible with a seeded generator
+    Iterations(usize),
+    /// Run iterations until the time has passed
+    Time(Duration),
+}
+
+#[derive(Debug, Clone)]
+enum Kind {
+    /// The player is about to slide, children are indexed like [`DIRECTIONS`]
+    Decision {
+        legal: [bool; 4],
+        children: [Option<usize>; 4],
+    },
+    /// A number is about to spawn, children are the boards seen so far
+    Chance {
+        reward: u64,
+        children: Vec<(ArrayBoard, usize)>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    board: ArrayBoard,
+    visits: u32,
+    total: f64,
+    kind: Kind,
+}
+
+/// Monte Carlo tree search with chance nodes
+///
+/// The tree alternates between decision nodes, where a direction is chosen
+/// using UCT, and chance nodes, where a number is spawned with
+/// [`Model::random`]. New nodes are valued by playing random legal directions
+/// until the game is over and values are the score gained from that point.
+///
+/// The tree is kept between calls to [`Mcts::best_direction`], and if the
+/// next board is one of the boards already reached through the chosen
+/// direction the search continues from that subtree.
+#[derive(Debug, Clone)]
+pub struct Mcts<R> {
+    rng: R,
+    budget: Budget,
+    exploration: f64,
+    nodes: Vec<Node>,
+    root: Option<usize>,
+}
+
+impl<R: Rng> Mcts<R> {
+    /// Create a new search with an exploration constant of √2
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Budget, Mcts};
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    /// use std::time::Duration;
+    ///
+    /// let by_iterations = Mcts::new(StdRng::seed_from_u64(42), Budget::Iterations(1000));
+    /// let by_time = Mcts::new(StdRng::seed_from_u64(42), Budget::Time(Duration::from_millis(50)));
+    /// ```
+    ///
+    pub fn new(rng: R, budget: Budget) -> Self {
+        Mcts {
+            rng,
+            budget,
+            exploration: std::f64::consts::SQRT_2,
+            nodes: Vec::new(),
+            root: None,
+        }
+    }
+
+    /// Set the UCT exploration constant
+    ///
+    /// Values are normalised by the best child before the exploration term is
+    /// added, so the constant does not depend on the size of the scores.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Budget, Mcts};
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let search = Mcts::new(StdRng::seed_from_u64(42), Budget::Iterations(1000)).exploration(0.5);
+    /// ```
+    ///
+    pub fn exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    /// Throw away the tree kept from earlier searches
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Budget, Mcts};
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut search = Mcts::new(StdRng::seed_from_u64(42), Budget::Iterations(1000));
+    /// search.reset();
+    /// ```
+    ///
+    pub fn reset(&mut self) {
+        self.nodes.clear();
+        self.root = None;
+    }
+
+    /// Search the game and return the most visited direction
+    ///
+    /// Returns `None` if no direction changes the board.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Budget, Mcts};
+    /// use game_2048_model::models::{ArrayModel, Directions, Model};
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(1);
+    /// let mut search = Mcts::new(StdRng::seed_from_u64(42), Budget::Iterations(100));
+    /// let mut game = ArrayModel::new();
+    /// game.random(&mut rng);
+    /// game.random(&mut rng);
+    ///
+    /// while let Some(direction) = search.best_direction(&game) {
+    ///     game.slide(direction);
+    ///     game.random(&mut rng);
+    /// #   break;
+    /// }
+    /// ```
+    ///
+    pub fn best_direction<M: Model>(&mut self, game: &M) -> Option<Directions> {
+        let board = game.as_array();
+        if DIRECTIONS
+            .iter()
+            .all(|direction| M::from(board).slide(*direction).is_none())
+        {
+            return None;
+        }
+        let root = self.reroot::<M>(board);
+
+        match self.budget {
+            Budget::Iterations(iterations) => {
+                for _ in 0..iterations {
+                    self.iterate::<M>(root);
+                }
+            }
+            Budget::Time(duration) => {
+                let start = Instant::now();
+                while start.elapsed() < duration {
+                    self.iterate::<M>(root);
+                }
+            }
+        }
+
+        let children = match &self.nodes[root].kind {
+            Kind::Decision { children, .. } => *children,
+            Kind::Chance { .. } => unreachable!("The root is always a decision node"),
+        };
+        let mut best: Option<(Directions, u32, f64)> = None;
+        for (direction, child) in DIRECTIONS.iter().zip(children.iter()) {
+            if let Some(child) = *child {
+                let visits = self.nodes[child].visits;
+                let value = self.value(child);
+                if best.is_none_or(|(_, best_visits, best_value)| {
+                    visits > best_visits || (visits == best_visits && value > best_value)
+                }) {
+                    best = Some((*direction, visits, value));
+                }
+            }
+        }
+        best.map(|(direction, _, _)| direction)
+    }
+
+    /// Find or create the root for the board, keeping only the reused subtree
+    fn reroot<M: Model>(&mut self, board: ArrayBoard) -> usize {
+        if let Some(root) = self.root {
+            if self.nodes[root].board == board {
+                return root;
+            }
+            let reused = self.grandchild(root, board);
+            if let Some(reused) = reused {
+                let mut nodes = Vec::new();
+                Mcts::<R>::copy_subtree(&self.nodes, reused, &mut nodes);
+                self.nodes = nodes;
+                self.root = Some(0);
+                return 0;
+            }
+        }
+
+        self.reset();
+        let root = self.add_decision::<M>(board);
+        self.root = Some(root);
+        root
+    }
+
+    /// The decision node two levels below the node with the given board
+    fn grandchild(&self, node: usize, board: ArrayBoard) -> Option<usize> {
+        if let Kind::Decision { children, .. } = &self.nodes[node].kind {
+            for child in children.iter().flatten() {
+                if let Kind::Chance { children, .. } = &self.nodes[*child].kind {
+                    if let Some((_, grandchild)) = children.iter().find(|(b, _)| *b == board) {
+                        return Some(*grandchild);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Copy the subtree starting at `node` to the end of `into`, returning its new index
+    fn copy_subtree(nodes: &[Node], node: usize, into: &mut Vec<Node>) -> usize {
+        let index = into.len();
+        into.push(nodes[node].clone());
+        let kind = match &nodes[node].kind {
+            Kind::Decision { legal, children } => {
+                let mut copied = [None; 4];
+                for (copy, child) in copied.iter_mut().zip(children.iter()) {
+                    *copy = child.map(|child| Mcts::<R>::copy_subtree(nodes, child, into));
+                }
+                Kind::Decision {
+                    legal: *legal,
+                    children: copied,
+                }
+            }
+            Kind::Chance { reward, children } => Kind::Chance {
+                reward: *reward,
+                children: children
+                    .iter()
+                    .map(|(board, child)| (*board, Mcts::<R>::copy_subtree(nodes, *child, into)))
+                    .collect(),
+            },
+        };
+        into[index].kind = kind;
+        index
+    }
+
+    fn add_decision<M: Model>(&mut self, board: ArrayBoard) -> usize {
+        let mut legal = [false; 4];
+        for (legal, direction) in legal.iter_mut().zip(DIRECTIONS.iter()) {
+            *legal = M::from(board).slide(*direction).is_some();
+        }
+        self.nodes.push(Node {
+            board,
+            visits: 0,
+            total: 0.0,
+            kind: Kind::Decision {
+                legal,
+                children: [None; 4],
+            },
+        });
+        self.nodes.len() - 1
+    }
+
+    fn add_chance(&mut self, board: ArrayBoard, reward: u64) -> usize {
+        self.nodes.push(Node {
+            board,
+            visits: 0,
+            total: 0.0,
+            kind: Kind::Chance {
+                reward,
+                children: Vec::new(),
+            },
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Expected score of a chance node, including the score of the slide leading to it
+    fn value(&self, node: usize) -> f64 {
+        let node = &self.nodes[node];
+        let reward = match node.kind {
+            Kind::Chance { reward, .. } => reward as f64,
+            Kind::Decision { .. } => 0.0,
+        };
+        if node.visits == 0 {
+            reward
+        } else {
+            reward + node.total / f64::from(node.visits)
+        }
+    }
+
+    /// Pick the child of a fully expanded decision node with the best UCT value
+    fn select(&self, node: usize, children: &[Option<usize>; 4]) -> usize {
+        let parent_visits = f64::from(self.nodes[node].visits.max(1));
+        let best_value = children
+            .iter()
+            .flatten()
+            .map(|child| self.value(*child))
+            .fold(0.0, f64::max);
+        let mut best: Option<(usize, f64)> = None;
+        for child in children.iter().flatten() {
+            let value = if best_value > 0.0 {
+                self.value(*child) / best_value
+            } else {
+                0.0
+            };
+            let visits = f64::from(self.nodes[*child].visits.max(1));
+            let uct = value + self.exploration * (parent_visits.ln() / visits).sqrt();
+            if best.is_none_or(|(_, best_uct)| uct > best_uct) {
+                best = Some((*child, uct));
+            }
+        }
+        best.expect("A fully expanded decision node has children").0
+    }
+
+    /// Run one selection, expansion, rollout and backpropagation pass
+    fn iterate<M: Model>(&mut self, root: usize) {
+        let mut path = vec![root];
+        let mut node = root;
+        let value = loop {
+            match &self.nodes[node].kind {
+                Kind::Decision { legal, children } => {
+                    let (legal, children) = (*legal, *children);
+                    if !legal.iter().any(|legal| *legal) {
+                        break 0;
+                    }
+                    let untried = (0..4).find(|i| legal[*i] && children[*i].is_none());
+                    if let Some(i) = untried {
+                        let board = self.nodes[node].board;
+                        let mut game = M::from(board);
+                        game.slide(DIRECTIONS[i]);
+                        let after = game.as_array();
+                        let child = self.add_chance(after, slide_score(&board, &after));
+                        if let Kind::Decision { children, .. } = &mut self.nodes[node].kind {
+                            children[i] = Some(child);
+                        }
+                        path.push(child);
+                        break self.rollout(game);
+                    }
+                    node = self.select(node, &children);
+                    path.push(node);
+                }
+                Kind::Chance { children, .. } => {
+                    let mut game = M::from(self.nodes[node].board);
+                    game.random(&mut self.rng)
+                        .expect("A board after a legal slide has an empty square");
+                    let spawned = game.as_array();
+                    if let Some((_, child)) = children.iter().find(|(b, _)| *b == spawned) {
+                        node = *child;
+                        path.push(node);
+                        continue;
+                    }
+                    let child = self.add_decision::<M>(spawned);
+                    if let Kind::Chance { children, .. } = &mut self.nodes[node].kind {
+                        children.push((spawned, child));
+                    }
+                    path.push(child);
+                    break self.rollout_decision(game);
+                }
+            }
+        };
+
+        let mut value = value as f64;
+        for node in path.iter().rev() {
+            let node = &mut self.nodes[*node];
+            node.visits += 1;
+            node.total += value;
+            if let Kind::Chance { reward, .. } = node.kind {
+                value += reward as f64;
+            }
+        }
+    }
+
+    /// Spawn a number and play random legal directions until the game is over
+    fn rollout<M: Model>(&mut self, mut game: M) -> u64 {
+        match game.random(&mut self.rng) {
+            Ok(()) => self.rollout_decision(game),
+            Err(NoEmptyError) => 0,
+        }
+    }
+
+    /// Play random legal directions until the game is over
+    fn rollout_decision<M: Model>(&mut self, mut game: M) -> u64 {
+        let mut score = 0;
+        let mut order = DIRECTIONS;
+        loop {
+            order.shuffle(&mut self.rng);
+            let before = game.as_array();
+            if !order
+                .iter()
+                .any(|direction| game.slide(*direction).is_some())
+            {
+                return score;
+            }
+            score += slide_score(&before, &game.as_array());
+            if game.random(&mut self.rng).is_err() {
+                return score;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Budget, Mcts};
+    use crate::models::{ArrayModel, Directions, Model};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::time::{Duration, Instant};
+
+    mod best_direction {
+        use super::*;
+
+        #[rustfmt::skip]
+        #[test]
+        fn returns_none_without_legal_moves() {
+            let game = ArrayModel::from([
+                1,2,1,2,
+                2,1,2,1,
+                1,2,1,2,
+                2,1,2,1
+            ]);
+            let mut search = Mcts::new(StdRng::seed_from_u64(1), Budget::Iterations(10));
+
+            assert_eq!(search.best_direction(&game), None);
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn returns_none_at_once_with_a_time_budget() {
+            let game = ArrayModel::from([
+                1,2,1,2,
+                2,1,2,1,
+                1,2,1,2,
+                2,1,2,1
+            ]);
+            let mut search = Mcts::new(StdRng::seed_from_u64(1), Budget::Time(Duration::from_secs(10)));
+            let start = Instant::now();
+
+            assert_eq!(search.best_direction(&game), None);
+            assert!(start.elapsed() < Duration::from_secs(1));
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn returns_the_only_legal_move() {
+            let game = ArrayModel::from([
+                0,2,1,2,
+                0,1,2,1,
+                0,2,1,2,
+                0,1,2,1
+            ]);
+            let mut search = Mcts::new(StdRng::seed_from_u64(1), Budget::Iterations(10));
+
+            assert_eq!(search.best_direction(&game), Some(Directions::Left));
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn same_seed_same_result() {
+            let game = ArrayModel::from([
+                1,0,2,0,
+                3,1,0,0,
+                0,0,1,0,
+                4,3,2,1
+            ]);
+            let mut first = Mcts::new(StdRng::seed_from_u64(9), Budget::Iterations(200));
+            let mut second = Mcts::new(StdRng::seed_from_u64(9), Budget::Iterations(200));
+
+            assert_eq!(first.best_direction(&game), second.best_direction(&game));
+            assert_eq!(first.nodes.len(), second.nodes.len());
+        }
+
+        #[test]
+        fn stops_after_time_budget() {
+            let game = ArrayModel::from([1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            let mut search = Mcts::new(
+                StdRng::seed_from_u64(1),
+                Budget::Time(Duration::from_millis(20)),
+            );
+
+            let start = Instant::now();
+            assert!(search.best_direction(&game).is_some());
+            assert!(start.elapsed() < Duration::from_secs(2));
+        }
+    }
+
+    mod tree_reuse {
+        use super::*;
+
+        #[test]
+        fn keeps_subtree_of_played_move() {
+            let mut rng = StdRng::seed_from_u64(5);
+            let mut game = ArrayModel::from([1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            let mut search = Mcts::new(StdRng::seed_from_u64(1), Budget::Iterations(500));
+
+            let direction = search.best_direction(&game).unwrap();
+            game.slide(direction);
+            game.random(&mut rng).unwrap();
+            let root = search.grandchild(search.root.unwrap(), game.as_array());
+            let visits = root.map(|root| search.nodes[root].visits);
+
+            search.best_direction(&game);
+
+            assert!(visits.is_some());
+            assert_eq!(search.nodes[0].visits, visits.unwrap() + 500);
+        }
+
+        #[test]
+        fn starts_over_on_unknown_board() {
+            let game = ArrayModel::from([1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            let other = ArrayModel::from([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 2]);
+            let mut search = Mcts::new(StdRng::seed_from_u64(1), Budget::Iterations(50));
+
+            search.best_direction(&game);
+            search.best_direction(&other);
+
+            assert_eq!(search.nodes[search.root.unwrap()].visits, 50);
+        }
+    }
+}
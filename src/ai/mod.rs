@@ -0,0 +1,43 @@
+//! Agents that choose which direction to play
+//!
+//! The agents work on any game model implementing [`Model`](crate::models::Model).
+//!
+//! ```
+//! use game_2048_model::ai::Expectimax;
+//! use game_2048_model::prelude::*;
+//!
+//! let game = Matrix::from([
+//!     [0, 0, 0, 0],
+//!     [4, 0, 0, 2],
+//!     [2, 8, 4, 0],
+//!     [3, 8, 6, 4]
+//! ]);
+//! let search = Expectimax::new(|board: &ArrayBoard| {
+//!     board.iter().filter(|x| **x == 0).count() as f64
+//! });
+//!
+//! assert!(search.best_direction(&game).is_some());
+//! ```
+
+mod agent;
+mod evaluation;
+mod expectimax;
+pub mod heuristics;
+mod hint;
+mod iterative_deepening;
+mod mcts;
+mod monte_carlo;
+mod parallel_expectimax;
+mod transposition;
+
+pub use agent::{Agent, Greedy, PreferenceOrder, RandomAgent};
+pub use evaluation::{play_game, Evaluation, GameResult, Report};
+pub(crate) use evaluation::play_moves;
+pub use expectimax::Expectimax;
+pub(crate) use expectimax::best_of;
+pub use hint::{hint, hint_with, Hint, Term};
+pub use iterative_deepening::{Deepening, IterativeDeepening, SearchBudget};
+pub use mcts::{Budget, Mcts};
+pub use monte_carlo::{MonteCarlo, RolloutObjective};
+pub use parallel_expectimax::ParallelExpectimax;
+pub use transposition::{pack, Entry, TranspositionTable};
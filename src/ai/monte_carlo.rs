@@ -0,0 +1,247 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use rand::prelude::*;
+
+use super::expectimax::best_of;
+use crate::base::*;
+
+/// What the random games played by [`MonteCarlo`] are compared by
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RolloutObjective {
+    /// The score gained by merges until the game is over
+    Score,
+    /// The number of moves played until the game is over
+    Moves,
+}
+
+/// Agent that plays random games to the end after each direction
+///
+/// Every legal direction is followed by a number of rollouts, games where
+/// numbers are spawned with [`Model::random`] and random legal directions are
+/// played until no direction changes the board. The direction with the best
+/// average result is chosen.
+#[derive(Debug, Clone)]
+pub struct MonteCarlo<R> {
+    rng: R,
+    rollouts: usize,
+    objective: RolloutObjective,
+}
+
+impl<R: Rng> MonteCarlo<R> {
+    /// Create a new agent playing `rollouts` games for every legal direction
+    ///
+    /// The agent compares the average score of the rollouts by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::MonteCarlo;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let agent = MonteCarlo::new(StdRng::seed_from_u64(42), 100);
+    /// ```
+    ///
+    pub fn new(rng: R, rollouts: usize) -> Self {
+        MonteCarlo {
+            rng,
+            rollouts,
+            objective: RolloutObjective::Score,
+        }
+    }
+
+    /// Set what the rollouts are compared by
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{MonteCarlo, RolloutObjective};
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let agent = MonteCarlo::new(StdRng::seed_from_u64(42), 100)
+    ///     .objective(RolloutObjective::Moves);
+    /// ```
+    ///
+    pub fn objective(mut self, objective: RolloutObjective) -> Self {
+        self.objective = objective;
+        self
+    }
+
+    /// Returns the direction with the best average rollout, or `None` if no direction changes the board
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::MonteCarlo;
+    /// use game_2048_model::models::{ArrayModel, Directions};
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let game = ArrayModel::from([
+    ///     0,2,1,2,
+    ///     0,1,2,1,
+    ///     0,2,1,2,
+    ///     0,1,2,1
+    /// ]);
+    /// let mut agent = MonteCarlo::new(StdRng::seed_from_u64(42), 10);
+    ///
+    /// assert_eq!(agent.best_direction(&game), Some(Directions::Left));
+    /// ```
+    ///
+    pub fn best_direction<M: Model>(&mut self, game: &M) -> Option<Directions> {
+        best_of(&self.evaluate(game))
+    }
+
+    /// Returns the average rollout result of each direction in the order of [`DIRECTIONS`]
+    ///
+    /// Directions that do not change the board are `None`. When comparing by
+    /// score the score of the first move is included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::MonteCarlo;
+    /// use game_2048_model::models::ArrayModel;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let game = ArrayModel::from([
+    ///     0,2,1,2,
+    ///     0,1,2,1,
+    ///     0,2,1,2,
+    ///     0,1,2,1
+    /// ]);
+    /// let values = MonteCarlo::new(StdRng::seed_from_u64(42), 10).evaluate(&game);
+    ///
+    /// assert!(values[0].is_none());
+    /// assert!(values[3].is_some());
+    /// ```
+    ///
+    pub fn evaluate<M: Model>(&mut self, game: &M) -> [Option<f64>; 4] {
+        let board = game.as_array();
+        let mut values = [None; 4];
+        for (value, direction) in values.iter_mut().zip(DIRECTIONS.iter()) {
+            let mut child = M::from(board);
+            if child.slide(*direction).is_none() {
+                continue;
+            }
+            let first = match self.objective {
+                RolloutObjective::Score => slide_score(&board, &child.as_array()),
+                RolloutObjective::Moves => 1,
+            };
+            let mut total = 0;
+            for _ in 0..self.rollouts {
+                total += first + self.rollout(M::from(child.as_array()));
+            }
+            *value = Some(total as f64 / self.rollouts.max(1) as f64);
+        }
+        values
+    }
+
+    /// Plays random legal directions until the game is over
+    fn rollout<M: Model>(&mut self, mut game: M) -> u64 {
+        let mut result = 0;
+        let mut order = DIRECTIONS;
+        while game.random(&mut self.rng).is_ok() {
+            order.shuffle(&mut self.rng);
+            let before = game.as_array();
+            match order
+                .iter()
+                .find(|direction| game.slide(**direction).is_some())
+            {
+                Some(_) => {
+                    result += match self.objective {
+                        RolloutObjective::Score => slide_score(&before, &game.as_array()),
+                        RolloutObjective::Moves => 1,
+                    };
+                }
+                None => break,
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MonteCarlo, RolloutObjective};
+    use crate::models::ArrayModel;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    mod best_direction {
+        use super::*;
+
+        #[rustfmt::skip]
+        #[test]
+        fn returns_none_without_legal_moves() {
+            let game = ArrayModel::from([
+                1,2,1,2,
+                2,1,2,1,
+                1,2,1,2,
+                2,1,2,1
+            ]);
+            let mut agent = MonteCarlo::new(StdRng::seed_from_u64(1), 10);
+
+            assert_eq!(agent.best_direction(&game), None);
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn same_seed_same_result() {
+            let board = [
+                1,0,2,0,
+                3,1,0,0,
+                0,0,1,0,
+                4,3,2,1
+            ];
+            let mut first = MonteCarlo::new(StdRng::seed_from_u64(7), 20);
+            let mut second = MonteCarlo::new(StdRng::seed_from_u64(7), 20);
+
+            assert_eq!(
+                first.evaluate(&ArrayModel::from(board)),
+                second.evaluate(&ArrayModel::from(board))
+            );
+        }
+    }
+
+    mod evaluate {
+        use super::*;
+
+        #[rustfmt::skip]
+        #[test]
+        fn includes_score_of_first_move() {
+            // Sliding left or right merges the two 2s into a 4 worth 8 points.
+            let game = ArrayModel::from([
+                2,2,3,4,
+                3,4,5,6,
+                4,5,6,7,
+                5,6,7,8
+            ]);
+            let values = MonteCarlo::new(StdRng::seed_from_u64(3), 5).evaluate(&game);
+
+            assert_eq!(values[0], None);
+            assert!(values[1].unwrap() >= 8.0);
+            assert_eq!(values[2], None);
+            assert!(values[3].unwrap() >= 8.0);
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn counts_moves() {
+            let game = ArrayModel::from([
+                2,2,3,4,
+                3,4,5,6,
+                4,5,6,7,
+                5,6,7,8
+            ]);
+            let values = MonteCarlo::new(StdRng::seed_from_u64(3), 5)
+                .objective(RolloutObjective::Moves)
+                .evaluate(&game);
+
+            assert!(values[3].unwrap() >= 1.0);
+        }
+    }
+}
@@ -0,0 +1,497 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+
+use super::expectimax::{best_of, spawns, NodeValue, Visitor};
+use super::heuristics::Heuristic;
+use super::{Entry, Expectimax, TranspositionTable};
+use crate::base::*;
+
+/// The value of a chance node together with how it was computed
+///
+/// A value computed without any probability cutoff in its subtree is exact for
+/// every probability at least as large, since a larger probability can only
+/// cut off fewer nodes. Other values are only reused for the same probability.
+#[derive(Debug, Copy, Clone)]
+struct Cached {
+    value: f64,
+    probability: f64,
+    pruned: bool,
+}
+
+impl Cached {
+    fn matches(&self, probability: f64) -> bool {
+        if self.pruned {
+            self.probability.to_bits() == probability.to_bits()
+        } else {
+            probability >= self.probability
+        }
+    }
+}
+
+/// Reads and writes chance node values in the shared table
+struct Shared<'a> {
+    table: &'a TranspositionTable<Cached>,
+}
+
+impl Visitor for Shared<'_> {
+    fn lookup(&mut self, board: &ArrayBoard, depth: usize, probability: f64) -> Option<NodeValue> {
+        self.table
+            .get(board)
+            .filter(|entry| entry.depth == depth && entry.value.matches(probability))
+            .map(|entry| NodeValue {
+                value: entry.value.value,
+                pruned: entry.value.pruned,
+            })
+    }
+
+    fn store(&mut self, board: &ArrayBoard, depth: usize, probability: f64, node: NodeValue) {
+        self.table.insert(
+            board,
+            Entry {
+                value: Cached {
+                    value: node.value,
+                    probability,
+                    pruned: node.pruned,
+                },
+                depth,
+                best_move: None,
+            },
+        );
+    }
+}
+
+/// A spawn below the root to be searched by one of the threads
+struct Task {
+    direction: usize,
+    board: ArrayBoard,
+    spawn_probability: f64,
+    probability: f64,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Worker threads kept running between searches
+struct Pool {
+    sender: Option<mpsc::Sender<Job>>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl Pool {
+    fn new(threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..threads)
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                thread::spawn(move || loop {
+                    let job = receiver.lock().expect("A worker panicked").recv();
+                    match job {
+                        // A panic drops the job's sender, which the search reports
+                        Ok(job) => drop(panic::catch_unwind(AssertUnwindSafe(job))),
+                        Err(_) => break,
+                    }
+                })
+            })
+            .collect();
+        Pool {
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    fn execute(&self, job: Job) {
+        self.sender
+            .as_ref()
+            .expect("The sender is only taken when dropping")
+            .send(job)
+            .expect("The workers run until the pool is dropped");
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("threads", &self.workers.len())
+            .finish()
+    }
+}
+
+/// Expectimax search spreading the work over several threads
+///
+/// Every spawn after each of the four root moves is searched as a separate
+/// task by the recursion of [`Expectimax`], and the threads share a
+/// [`TranspositionTable`] of chance node values, so positions reached through
+/// different spawn orders are only searched once. The values are added up in
+/// the same order as [`Expectimax`] does, and the table only returns values
+/// that the sequential search would have computed, so the results are
+/// identical to the sequential search with the same settings.
+///
+/// The threads are started by the first search and kept for the next ones,
+/// as is the table, since the positions searched after a move are mostly the
+/// ones searched below it the move before. Clones share both.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::{Expectimax, ParallelExpectimax};
+/// use game_2048_model::models::Matrix;
+///
+/// let game = Matrix::from([
+///     [0, 0, 0, 0],
+///     [4, 0, 0, 2],
+///     [2, 8, 4, 0],
+///     [3, 8, 6, 4]
+/// ]);
+/// let search = Expectimax::default().depth(2);
+/// let parallel = ParallelExpectimax::new(Expectimax::default().depth(2)).threads(4);
+///
+/// assert_eq!(parallel.evaluate(&game), search.evaluate(&game));
+/// ```
+///
+#[derive(Debug)]
+pub struct ParallelExpectimax<E> {
+    search: Arc<Expectimax<E>>,
+    threads: usize,
+    table: Arc<TranspositionTable<Cached>>,
+    pool: OnceLock<Arc<Pool>>,
+}
+
+impl<E> Clone for ParallelExpectimax<E> {
+    fn clone(&self) -> Self {
+        ParallelExpectimax {
+            search: Arc::clone(&self.search),
+            threads: self.threads,
+            table: Arc::clone(&self.table),
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+impl<E> ParallelExpectimax<E>
+where
+    E: Heuristic + Send + Sync + 'static,
+{
+    /// Run the search on one thread for every available core
+    ///
+    /// The table of chance node values defaults to 65536 slots.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Expectimax, ParallelExpectimax};
+    ///
+    /// let search = ParallelExpectimax::new(Expectimax::default().depth(4));
+    /// ```
+    ///
+    pub fn new(search: Expectimax<E>) -> Self {
+        ParallelExpectimax {
+            search: Arc::new(search),
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            table: Arc::new(TranspositionTable::new(1 << 16)),
+            pool: OnceLock::new(),
+        }
+    }
+
+    /// Set the number of threads searching, at least 1
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Expectimax, ParallelExpectimax};
+    ///
+    /// let search = ParallelExpectimax::new(Expectimax::default()).threads(8);
+    /// ```
+    ///
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self.pool = OnceLock::new();
+        self
+    }
+
+    /// Set the number of slots in the table of chance node values, clearing it
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slots` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Expectimax, ParallelExpectimax};
+    ///
+    /// let search = ParallelExpectimax::new(Expectimax::default()).table_size(1 << 20);
+    /// ```
+    ///
+    pub fn table_size(mut self, slots: usize) -> Self {
+        self.table = Arc::new(TranspositionTable::new(slots));
+        self
+    }
+
+    /// Returns the best direction to play, or `None` if no direction changes the board
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Expectimax, ParallelExpectimax};
+    /// use game_2048_model::models::{Directions, Matrix};
+    /// use game_2048_model::ArrayBoard;
+    ///
+    /// let game = Matrix::from([
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     1,2,3,4
+    /// ]);
+    /// let search = Expectimax::new(|board: &ArrayBoard| {
+    ///     board.iter().filter(|x| **x == 0).count() as f64
+    /// });
+    ///
+    /// assert_eq!(
+    ///     ParallelExpectimax::new(search).best_direction(&game),
+    ///     Some(Directions::Up)
+    /// );
+    /// ```
+    ///
+    pub fn best_direction<M: Model + 'static>(&self, game: &M) -> Option<Directions> {
+        best_of(&self.evaluate(game))
+    }
+
+    /// Returns the expected value of each direction in the order of [`DIRECTIONS`]
+    ///
+    /// Directions that do not change the board are `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Expectimax, ParallelExpectimax};
+    /// use game_2048_model::models::Matrix;
+    /// use game_2048_model::ArrayBoard;
+    ///
+    /// let game = Matrix::from([
+    ///     1,0,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,0
+    /// ]);
+    /// let search = Expectimax::new(|_: &ArrayBoard| 1.0).depth(2);
+    ///
+    /// assert_eq!(
+    ///     ParallelExpectimax::new(search).evaluate(&game),
+    ///     [None, Some(1.0), Some(1.0), None]
+    /// );
+    /// ```
+    ///
+    pub fn evaluate<M: Model + 'static>(&self, game: &M) -> [Option<f64>; 4] {
+        let board = game.as_array();
+        let depth = self.search.depth - 1;
+        let mut values = [None; 4];
+        let mut empty = [0; 4];
+        let mut tasks = Vec::new();
+
+        for (index, direction) in DIRECTIONS.iter().enumerate() {
+            let mut child = M::from(board);
+            if child.slide(*direction).is_none() {
+                continue;
+            }
+            let child = child.as_array();
+            if let Some(leaf) = self.search.chance_leaf(&mut (), &child, depth, 1.0) {
+                values[index] = Some(leaf.value);
+                continue;
+            }
+            empty[index] = child.iter().filter(|x| **x == 0).count();
+            for (spawned, spawn_probability, probability) in spawns(child, 1.0) {
+                tasks.push(Task {
+                    direction: index,
+                    board: spawned,
+                    spawn_probability,
+                    probability,
+                });
+            }
+        }
+
+        let tasks = Arc::new(tasks);
+        let results = self.run_tasks::<M>(&tasks, depth);
+
+        // Add up in the same order as the sequential chance node
+        let mut totals = [0.0; 4];
+        for (task, value) in tasks.iter().zip(results.iter()) {
+            totals[task.direction] += task.spawn_probability * value;
+        }
+        for index in 0..4 {
+            if values[index].is_none() && empty[index] > 0 {
+                values[index] = Some(totals[index] / empty[index] as f64);
+            }
+        }
+        values
+    }
+
+    /// The value of every task, in the order of the tasks
+    fn run_tasks<M: Model + 'static>(&self, tasks: &Arc<Vec<Task>>, depth: usize) -> Vec<f64> {
+        let pool = self.pool.get_or_init(|| Arc::new(Pool::new(self.threads)));
+        let next = Arc::new(AtomicUsize::new(0));
+        let (sender, receiver) = mpsc::channel();
+
+        for _ in 0..self.threads.min(tasks.len()) {
+            let search = Arc::clone(&self.search);
+            let table = Arc::clone(&self.table);
+            let tasks = Arc::clone(tasks);
+            let next = Arc::clone(&next);
+            let sender = sender.clone();
+            pool.execute(Box::new(move || {
+                let mut shared = Shared { table: &table };
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let task = match tasks.get(index) {
+                        Some(task) => task,
+                        None => break,
+                    };
+                    let node = search
+                        .max_node::<M, _>(&mut shared, task.board, depth, task.probability)
+                        .expect("The shared table never stops the search");
+                    if sender.send((index, node.value)).is_err() {
+                        break;
+                    }
+                }
+            }));
+        }
+        drop(sender);
+
+        let mut results = vec![0.0; tasks.len()];
+        for _ in 0..tasks.len() {
+            let (index, value) = receiver.recv().expect("A search thread panicked");
+            results[index] = value;
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expectimax, ParallelExpectimax};
+    use crate::ai::heuristics::Weighted;
+    use crate::models::{ArrayModel, Matrix, Model};
+    use crate::ArrayBoard;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::sync::Arc;
+
+    #[rustfmt::skip]
+    const BOARD: [u8; 16] = [
+        1,0,2,0,
+        3,1,0,0,
+        0,0,1,0,
+        4,3,2,1
+    ];
+
+    mod evaluate {
+        use super::*;
+
+        #[test]
+        fn identical_to_sequential_search() {
+            for depth in 1..=3 {
+                let search = Expectimax::default().depth(depth);
+                let parallel =
+                    ParallelExpectimax::new(Expectimax::default().depth(depth)).threads(4);
+                let game = ArrayModel::from(BOARD);
+
+                assert_eq!(parallel.evaluate(&game), search.evaluate(&game));
+            }
+        }
+
+        #[test]
+        fn identical_to_sequential_search_with_cutoff() {
+            let search = || {
+                Expectimax::new(Weighted::standard())
+                    .depth(3)
+                    .probability_cutoff(0.01)
+            };
+            let game = Matrix::from(BOARD);
+
+            for threads in 1..=3 {
+                let parallel = ParallelExpectimax::new(search()).threads(threads);
+                assert_eq!(parallel.evaluate(&game), search().evaluate(&game));
+            }
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn identical_when_a_move_ends_the_game() {
+            let game = ArrayModel::from([
+                4,2,0,6,
+                1,4,5,3,
+                6,5,4,5,
+                2,1,6,1
+            ]);
+            let search = || Expectimax::new(|_: &ArrayBoard| -1.0).depth(2);
+            let parallel = ParallelExpectimax::new(search()).threads(2);
+
+            assert_eq!(parallel.evaluate(&game), search().evaluate(&game));
+            assert!(parallel.evaluate(&game)[3].unwrap() < -1.0e8);
+        }
+
+        #[test]
+        fn keeps_its_threads_between_searches() {
+            let parallel = ParallelExpectimax::new(Expectimax::default().depth(2)).threads(2);
+            let game = ArrayModel::from(BOARD);
+
+            parallel.evaluate(&game);
+            let pool = Arc::clone(parallel.pool.get().unwrap());
+            parallel.evaluate(&game);
+            parallel.clone().evaluate(&game);
+
+            assert!(Arc::ptr_eq(&pool, parallel.pool.get().unwrap()));
+            assert_eq!(pool.workers.len(), 2);
+        }
+
+        #[test]
+        fn identical_over_a_game() {
+            let search = Expectimax::default().depth(2);
+            let parallel = ParallelExpectimax::new(Expectimax::default().depth(2)).threads(3);
+            let mut rng = StdRng::seed_from_u64(6);
+            let mut game = ArrayModel::new();
+            game.random(&mut rng).unwrap();
+            game.random(&mut rng).unwrap();
+
+            for _ in 0..40 {
+                assert_eq!(parallel.evaluate(&game), search.evaluate(&game));
+                match search.best_direction(&game) {
+                    Some(direction) => game.slide(direction),
+                    None => break,
+                };
+                game.random(&mut rng).unwrap();
+            }
+        }
+    }
+
+    mod best_direction {
+        use super::*;
+
+        #[rustfmt::skip]
+        #[test]
+        fn returns_none_without_legal_moves() {
+            let game = ArrayModel::from([
+                1,2,1,2,
+                2,1,2,1,
+                1,2,1,2,
+                2,1,2,1
+            ]);
+
+            assert_eq!(ParallelExpectimax::new(Expectimax::default()).best_direction(&game), None);
+        }
+    }
+}
@@ -0,0 +1,412 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::base::*;
+
+/// Pack a board into a `u64` with four bits per square, in array order
+///
+/// Returns `None` if an exponent does not fit in four bits, that is if the
+/// board holds a number of 2^16 = 65536 or more.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::pack;
+///
+/// let board = [
+///     1,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,15
+/// ];
+///
+/// assert_eq!(pack(&board), Some(0xf000_0000_0000_0001));
+/// assert_eq!(pack(&[16; 16]), None);
+/// ```
+///
+pub fn pack(board: &ArrayBoard) -> Option<u64> {
+    let mut key = 0;
+    for (square, exponent) in board.iter().enumerate() {
+        if *exponent > 15 {
+            return None;
+        }
+        key |= u64::from(*exponent) << (4 * square);
+    }
+    Some(key)
+}
+
+/// What a search stored about a board
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Entry<V> {
+    /// The value found by the search
+    pub value: V,
+    /// The depth the board was searched to
+    pub depth: usize,
+    /// The best direction found, if any
+    pub best_move: Option<Directions>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Slot<V> {
+    key: u64,
+    entry: Entry<V>,
+}
+
+/// A fixed size table of search results keyed by board
+///
+/// Boards are keyed by their [`pack`]ed form, and every key has a single slot
+/// picked by its hash. When two boards share a slot the entry searched to the
+/// larger depth is kept. Boards holding a number of 65536 or more are never
+/// stored.
+///
+/// With symmetry folding the eight rotations and reflections of a board share
+/// an entry, and the best move is turned to match the board it is looked up
+/// with. Folding is only correct if the stored values are the same for
+/// symmetric boards.
+///
+/// The table only needs a shared reference, so it can be used by several
+/// search threads at once.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::{Entry, TranspositionTable};
+/// use game_2048_model::models::Directions;
+///
+/// let table = TranspositionTable::new(1024);
+/// let board = [
+///     1,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0
+/// ];
+/// table.insert(&board, Entry { value: 2.5, depth: 3, best_move: Some(Directions::Right) });
+///
+/// assert_eq!(table.get(&board).map(|entry| entry.value), Some(2.5));
+/// ```
+///
+pub struct TranspositionTable<V> {
+    slots: Vec<Mutex<Option<Slot<V>>>>,
+    fold_symmetries: bool,
+}
+
+impl<V: Copy> TranspositionTable<V> {
+    /// Create an empty table with a fixed number of slots
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slots` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::TranspositionTable;
+    ///
+    /// let table: TranspositionTable<f64> = TranspositionTable::new(1 << 16);
+    ///
+    /// assert_eq!(table.capacity(), 1 << 16);
+    /// ```
+    ///
+    pub fn new(slots: usize) -> Self {
+        assert!(slots > 0, "The table needs at least one slot");
+        TranspositionTable {
+            slots: (0..slots).map(|_| Mutex::new(None)).collect(),
+            fold_symmetries: false,
+        }
+    }
+
+    /// Let the eight rotations and reflections of a board share an entry
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Entry, TranspositionTable};
+    /// use game_2048_model::models::Directions;
+    ///
+    /// let table = TranspositionTable::new(1024).fold_symmetries(true);
+    /// let board = [
+    ///     1,0,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,0
+    /// ];
+    /// let mirrored = [
+    ///     0,0,0,1,
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,0
+    /// ];
+    /// table.insert(&board, Entry { value: 1.0, depth: 1, best_move: Some(Directions::Right) });
+    ///
+    /// assert_eq!(
+    ///     table.get(&mirrored),
+    ///     Some(Entry { value: 1.0, depth: 1, best_move: Some(Directions::Left) })
+    /// );
+    /// ```
+    ///
+    pub fn fold_symmetries(mut self, fold_symmetries: bool) -> Self {
+        self.fold_symmetries = fold_symmetries;
+        self
+    }
+
+    /// The number of slots in the table
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::TranspositionTable;
+    ///
+    /// let table: TranspositionTable<f64> = TranspositionTable::new(100);
+    ///
+    /// assert_eq!(table.capacity(), 100);
+    /// ```
+    ///
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the entry stored for the board, if it is still in the table
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::TranspositionTable;
+    ///
+    /// let table: TranspositionTable<f64> = TranspositionTable::new(100);
+    ///
+    /// assert_eq!(table.get(&[0; 16]), None);
+    /// ```
+    ///
+    pub fn get(&self, board: &ArrayBoard) -> Option<Entry<V>> {
+        let (key, symmetry) = self.key(board)?;
+        let slot = *self.slot(key).lock().expect("A search thread panicked");
+        slot.filter(|slot| slot.key == key).map(|slot| {
+            let mut entry = slot.entry;
+            entry.best_move = entry
+                .best_move
+                .map(|direction| original_direction(direction, symmetry));
+            entry
+        })
+    }
+
+    /// Store the entry for the board
+    ///
+    /// The entry replaces the one in its slot unless that one was searched
+    /// deeper. Returns whether the entry was stored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Entry, TranspositionTable};
+    ///
+    /// let table = TranspositionTable::new(100);
+    /// let board = [0; 16];
+    ///
+    /// assert!(table.insert(&board, Entry { value: 1.0, depth: 4, best_move: None }));
+    /// assert!(!table.insert(&board, Entry { value: 2.0, depth: 2, best_move: None }));
+    /// assert_eq!(table.get(&board).map(|entry| entry.value), Some(1.0));
+    /// ```
+    ///
+    pub fn insert(&self, board: &ArrayBoard, mut entry: Entry<V>) -> bool {
+        let (key, symmetry) = match self.key(board) {
+            Some(key) => key,
+            None => return false,
+        };
+        entry.best_move = entry
+            .best_move
+            .map(|direction| symmetric_direction(direction, symmetry));
+
+        let mut slot = self.slot(key).lock().expect("A search thread panicked");
+        if slot.is_some_and(|slot| slot.entry.depth > entry.depth) {
+            return false;
+        }
+        *slot = Some(Slot { key, entry });
+        true
+    }
+
+    /// Remove every entry
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{Entry, TranspositionTable};
+    ///
+    /// let table = TranspositionTable::new(100);
+    /// table.insert(&[0; 16], Entry { value: 1.0, depth: 1, best_move: None });
+    /// table.clear();
+    ///
+    /// assert_eq!(table.get(&[0; 16]), None);
+    /// ```
+    ///
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            *slot.lock().expect("A search thread panicked") = None;
+        }
+    }
+
+    /// The key of the board and the symmetry turning the board into the stored one
+    fn key(&self, board: &ArrayBoard) -> Option<(u64, usize)> {
+        let symmetries = if self.fold_symmetries { 8 } else { 1 };
+        let mut best: Option<(u64, usize)> = None;
+        for symmetry in 0..symmetries {
+            let mut image = [0; BOARD_SIZE * BOARD_SIZE];
+            for (square, exponent) in board.iter().enumerate() {
+                image[symmetric_square(square, symmetry)] = *exponent;
+            }
+            let key = pack(&image)?;
+            if best.is_none_or(|(best_key, _)| key < best_key) {
+                best = Some((key, symmetry));
+            }
+        }
+        best
+    }
+
+    fn slot(&self, key: u64) -> &Mutex<Option<Slot<V>>> {
+        // Fibonacci hashing spreads boards differing in a single square over the table
+        let hash = key.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32;
+        &self.slots[hash as usize % self.slots.len()]
+    }
+}
+
+impl<V: Copy> Clone for TranspositionTable<V> {
+    fn clone(&self) -> Self {
+        TranspositionTable {
+            slots: self
+                .slots
+                .iter()
+                .map(|slot| Mutex::new(*slot.lock().expect("A search thread panicked")))
+                .collect(),
+            fold_symmetries: self.fold_symmetries,
+        }
+    }
+}
+
+impl<V> fmt::Debug for TranspositionTable<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TranspositionTable")
+            .field("capacity", &self.slots.len())
+            .field("fold_symmetries", &self.fold_symmetries)
+            .finish()
+    }
+}
+
+/// The direction on the original board matching a direction on the symmetric board
+fn original_direction(direction: Directions, symmetry: usize) -> Directions {
+    *DIRECTIONS
+        .iter()
+        .find(|original| symmetric_direction(**original, symmetry) == direction)
+        .expect("Every symmetry maps the directions one to one")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pack, Entry, TranspositionTable};
+    use crate::models::Directions;
+
+    #[rustfmt::skip]
+    const BOARD: [u8; 16] = [
+        1,0,2,0,
+        3,1,0,0,
+        0,0,1,0,
+        4,3,0,1
+    ];
+
+    fn entry(depth: usize) -> Entry<f64> {
+        Entry {
+            value: depth as f64,
+            depth,
+            best_move: Some(Directions::Up),
+        }
+    }
+
+    mod pack {
+        use super::pack;
+
+        #[test]
+        fn distinct_boards_distinct_keys() {
+            let mut board = [0; 16];
+            let empty = pack(&board);
+            board[15] = 1;
+
+            assert_ne!(pack(&board), empty);
+        }
+    }
+
+    mod insert {
+        use super::*;
+
+        #[test]
+        fn replaces_shallower_entries() {
+            let table = TranspositionTable::new(16);
+
+            assert!(table.insert(&BOARD, entry(2)));
+            assert!(table.insert(&BOARD, entry(2)));
+            assert!(table.insert(&BOARD, entry(3)));
+            assert!(!table.insert(&BOARD, entry(1)));
+            assert_eq!(table.get(&BOARD), Some(entry(3)));
+        }
+
+        #[test]
+        fn colliding_boards_keep_the_deeper_entry() {
+            let table = TranspositionTable::new(1);
+            let other = [0; 16];
+            table.insert(&BOARD, entry(3));
+            table.insert(&other, entry(2));
+
+            assert_eq!(table.get(&BOARD), Some(entry(3)));
+            assert_eq!(table.get(&other), None);
+        }
+
+        #[test]
+        fn ignores_boards_that_do_not_pack() {
+            let table = TranspositionTable::new(16);
+
+            assert!(!table.insert(&[16; 16], entry(1)));
+            assert_eq!(table.get(&[16; 16]), None);
+        }
+    }
+
+    mod get {
+        use super::*;
+        use crate::base::{symmetric_direction, symmetric_square, DIRECTIONS};
+
+        #[test]
+        fn symmetric_boards_are_separate_without_folding() {
+            let table = TranspositionTable::new(1024);
+            let mut transposed = [0; 16];
+            for (square, x) in BOARD.iter().enumerate() {
+                transposed[symmetric_square(square, 4)] = *x;
+            }
+            table.insert(&BOARD, entry(1));
+
+            assert_eq!(table.get(&transposed), None);
+        }
+
+        #[test]
+        fn folding_turns_the_best_move() {
+            for symmetry in 0..8 {
+                for direction in DIRECTIONS.iter() {
+                    let table = TranspositionTable::new(1024).fold_symmetries(true);
+                    let mut image = [0; 16];
+                    for (square, x) in BOARD.iter().enumerate() {
+                        image[symmetric_square(square, symmetry)] = *x;
+                    }
+                    table.insert(
+                        &BOARD,
+                        Entry {
+                            value: 1.0,
+                            depth: 1,
+                            best_move: Some(*direction),
+                        },
+                    );
+
+                    let found = table.get(&image).unwrap().best_move;
+                    assert_eq!(found, Some(symmetric_direction(*direction, symmetry)));
+                }
+            }
+        }
+    }
+}
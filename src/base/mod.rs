@@ -13,6 +13,7 @@ pub type ArrayBoardIndex = [usize; BOARD_SIZE * BOARD_SIZE];
 // The board is represented as a matrix defined as an array of arrays
 pub type MatrixBoard = [[BoardElement; BOARD_SIZE]; BOARD_SIZE];
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Directions {
     Up,
     Right,
@@ -20,6 +21,109 @@ pub enum Directions {
     Left,
 }
 
+// All directions in the order they are tried by the agents
+pub const DIRECTIONS: [Directions; 4] = [
+    Directions::Up,
+    Directions::Right,
+    Directions::Down,
+    Directions::Left,
+];
+
+// The exponents spawned by `Model::random` together with their probability
+pub const SPAWN_PROBABILITIES: [(BoardElement, f64); 2] = [(1, 0.9), (2, 0.1)];
+
+// The score gained by the merges of a single slide, given the board before and after it.
+//
+// Every number 2^n built only from merges has added (n - 1) * 2^n to the score, and since
+// a slide does not spawn any numbers the difference between the boards is exactly the
+// value of the merges.
+pub fn slide_score(before: &ArrayBoard, after: &ArrayBoard) -> u64 {
+    fn merged_value(board: &ArrayBoard) -> u64 {
+        board
+            .iter()
+            .filter(|x| **x > 1)
+            .map(|x| u64::from(*x - 1) << *x)
+            .sum()
+    }
+    merged_value(after) - merged_value(before)
+}
+
+// Slide a line of any length towards its first square with the rules of `Model::slide`.
+//
+// Numbers move over empty squares and two equal numbers merge into the next exponent, every
+// number merging at most once. `merged` is called with the exponent of every number built by
+// a merge, in the order of the line.
+pub(crate) fn merge_line<F: FnMut(BoardElement)>(line: &mut [BoardElement], mut merged: F) {
+    let mut next = 0;
+    let mut pending: Option<BoardElement> = None;
+    for index in 0..line.len() {
+        let value = line[index];
+        if value == 0 {
+            continue;
+        }
+        // Every square before `index` holding a number was already moved out of the way
+        line[index] = 0;
+        match pending {
+            Some(previous) if previous == value => {
+                line[next] = value + 1;
+                next += 1;
+                merged(value + 1);
+                pending = None;
+            }
+            Some(previous) => {
+                line[next] = previous;
+                next += 1;
+                pending = Some(value);
+            }
+            None => pending = Some(value),
+        }
+    }
+    if let Some(previous) = pending {
+        line[next] = previous;
+    }
+}
+
+// The square a square ends up on after one of the eight symmetries of the board.
+//
+// Bit 4 of the symmetry transposes the board, then bit 2 flips the rows and bit 1 flips
+// the columns.
+pub(crate) fn symmetric_square(square: usize, symmetry: usize) -> usize {
+    let last = BOARD_SIZE - 1;
+    let (row, col) = (square / BOARD_SIZE, square % BOARD_SIZE);
+    let (row, col) = if symmetry & 4 != 0 {
+        (col, row)
+    } else {
+        (row, col)
+    };
+    let row = if symmetry & 2 != 0 { last - row } else { row };
+    let col = if symmetry & 1 != 0 { last - col } else { col };
+    row * BOARD_SIZE + col
+}
+
+// The direction that slides the symmetric board the same way as the direction slides the
+// original board, using the same symmetries as `symmetric_square`.
+pub(crate) fn symmetric_direction(direction: Directions, symmetry: usize) -> Directions {
+    let (row, col): (i8, i8) = match direction {
+        Directions::Up => (-1, 0),
+        Directions::Right => (0, 1),
+        Directions::Down => (1, 0),
+        Directions::Left => (0, -1),
+    };
+    let (row, col) = if symmetry & 4 != 0 {
+        (col, row)
+    } else {
+        (row, col)
+    };
+    let row = if symmetry & 2 != 0 { -row } else { row };
+    let col = if symmetry & 1 != 0 { -col } else { col };
+    match (row, col) {
+        (-1, 0) => Directions::Up,
+        (0, 1) => Directions::Right,
+        (1, 0) => Directions::Down,
+        _ => Directions::Left,
+    }
+}
+
 pub trait Model: From<MatrixBoard> + From<ArrayBoard> {
     fn new() -> Self;
 
@@ -31,3 +135,142 @@ pub trait Model: From<MatrixBoard> + From<ArrayBoard> {
 
     fn as_array(&self) -> ArrayBoard;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_line, slide_score, symmetric_direction, symmetric_square, DIRECTIONS};
+
+    mod slide_score {
+        use super::slide_score;
+
+        #[rustfmt::skip]
+        #[test]
+        fn counts_the_value_of_merged_numbers() {
+            let before = [
+                1,1,2,2,
+                3,3,0,0,
+                0,0,0,0,
+                0,0,0,0
+            ];
+            let after = [
+                2,3,0,0,
+                4,0,0,0,
+                0,0,0,0,
+                0,0,0,0
+            ];
+
+            assert_eq!(slide_score(&before, &after), 4 + 8 + 16);
+        }
+
+        #[test]
+        fn zero_without_merges() {
+            let board = [1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 5];
+
+            assert_eq!(slide_score(&board, &board), 0);
+        }
+    }
+
+    mod merge_line {
+        use super::{merge_line, slide_score};
+        use crate::models::{ArrayModel, Directions, Model};
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        #[test]
+        fn merges_each_number_once() {
+            let mut line = [1, 1, 1, 1];
+            let mut merges = Vec::new();
+            merge_line(&mut line, |exponent| merges.push(exponent));
+
+            assert_eq!(line, [2, 2, 0, 0]);
+            assert_eq!(merges, vec![2, 2]);
+        }
+
+        #[test]
+        fn works_on_short_lines() {
+            let mut line = [0, 3, 3];
+            merge_line(&mut line, |_| {});
+
+            assert_eq!(line, [4, 0, 0]);
+        }
+
+        #[test]
+        fn slides_like_the_engine() {
+            let mut rng = StdRng::seed_from_u64(7);
+            for _ in 0..500 {
+                let mut board = [0; 16];
+                for x in board.iter_mut() {
+                    *x = rng.gen_range(0, 4);
+                }
+                let mut game = ArrayModel::from(board);
+                game.slide(Directions::Left);
+
+                let mut slid = board;
+                let mut score = 0;
+                for row in slid.chunks_mut(4) {
+                    merge_line(row, |exponent| score += 1 << exponent);
+                }
+
+                assert_eq!(slid, game.as_array());
+                assert_eq!(score, slide_score(&board, &game.as_array()));
+            }
+        }
+    }
+
+    mod symmetric_square {
+        use super::symmetric_square;
+
+        #[test]
+        fn every_symmetry_is_a_permutation() {
+            for symmetry in 0..8 {
+                let mut squares: Vec<usize> = (0..16)
+                    .map(|square| symmetric_square(square, symmetry))
+                    .collect();
+                squares.sort();
+                assert_eq!(squares, (0..16).collect::<Vec<usize>>());
+            }
+        }
+
+        #[test]
+        fn symmetries_are_distinct() {
+            let mut images: Vec<Vec<usize>> = (0..8)
+                .map(|symmetry| {
+                    (0..16)
+                        .map(|square| symmetric_square(square, symmetry))
+                        .collect()
+                })
+                .collect();
+            images.sort();
+            images.dedup();
+            assert_eq!(images.len(), 8);
+        }
+    }
+
+    mod symmetric_direction {
+        use super::{symmetric_direction, symmetric_square, DIRECTIONS};
+        use crate::models::{ArrayModel, Model};
+
+        #[test]
+        fn slides_the_symmetric_board_the_same_way() {
+            let board = [1, 0, 2, 0, 3, 1, 0, 0, 0, 0, 1, 0, 4, 3, 0, 1];
+            let transform = |board: [u8; 16], symmetry| {
+                let mut image = [0; 16];
+                for (square, x) in board.iter().enumerate() {
+                    image[symmetric_square(square, symmetry)] = *x;
+                }
+                image
+            };
+
+            for symmetry in 0..8 {
+                for direction in DIRECTIONS.iter() {
+                    let mut game = ArrayModel::from(board);
+                    game.slide(*direction);
+                    let mut image = ArrayModel::from(transform(board, symmetry));
+                    image.slide(symmetric_direction(*direction, symmetry));
+
+                    assert_eq!(image.as_array(), transform(game.as_array(), symmetry));
+                }
+            }
+        }
+    }
+}
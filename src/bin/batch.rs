@@ -0,0 +1,396 @@
+//! Play many games with an agent and print statistics
+//!
+//! ```text
+//! batch --agent expectimax --games 100 --seed 0 --threads 4 --replays games/
+//! ```
+//!
+//! Game `i` is played with seed `seed + i`, wrapping around after `u64::MAX`,
+//! so a run gives the same results no matter the number of threads. Run `batch --help` for every flag.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Instant;
+
+use game_2048_model::ai::heuristics::{
+    EmptySquares, MaxInCorner, MergePotential, Monotonicity, Smoothness, SnakePattern, Weighted,
+};
+use game_2048_model::ai::{
+    Agent, Evaluation, Expectimax, GameResult, Greedy, PreferenceOrder, RandomAgent, Report,
+};
+use game_2048_model::models::{ArrayModel, Directions, Model};
+use game_2048_model::replay::Replay;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const USAGE: &str = "Usage: batch [options]
+
+Options:
+  --agent <name>               random, corner, greedy or expectimax [default: expectimax]
+  --games <n>                  Number of games to play [default: 100]
+  --seed <seed>                Seed of the first game [default: 0]
+  --threads <n>                Threads playing games [default: available cores]
+  --replays <directory>        Write every game to <directory>/<seed>.replay
+
+corner:
+  --order <letters>            Directions to try in order, from U, R, D and L [default: DLRU]
+
+expectimax:
+  --depth <n>                  Moves to look ahead [default: 2]
+  --probability-cutoff <p>     Probability below which spawns are not expanded [default: 0.0001]
+  --empty-squares <weight>     [default: 2.7]
+  --monotonicity <weight>      [default: 1.0]
+  --smoothness <weight>        [default: 0.1]
+  --merge-potential <weight>   [default: 0.7]
+  --max-in-corner <weight>     [default: 1.0]
+  --snake-pattern <weight>     [default: 0.0]";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Strategy {
+    Random,
+    Corner,
+    Greedy,
+    Expectimax,
+}
+
+/// The weights of the expectimax evaluation, the defaults are [`Weighted::standard`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Weights {
+    empty_squares: f64,
+    monotonicity: f64,
+    smoothness: f64,
+    merge_potential: f64,
+    max_in_corner: f64,
+    snake_pattern: f64,
+}
+
+impl Weights {
+    fn build(&self) -> Weighted {
+        let mut weighted = Weighted::new();
+        if self.empty_squares != 0.0 {
+            weighted = weighted.add(self.empty_squares, EmptySquares);
+        }
+        if self.monotonicity != 0.0 {
+            weighted = weighted.add(self.monotonicity, Monotonicity);
+        }
+        if self.smoothness != 0.0 {
+            weighted = weighted.add(self.smoothness, Smoothness);
+        }
+        if self.merge_potential != 0.0 {
+            weighted = weighted.add(self.merge_potential, MergePotential);
+        }
+        if self.max_in_corner != 0.0 {
+            weighted = weighted.add(self.max_in_corner, MaxInCorner);
+        }
+        if self.snake_pattern != 0.0 {
+            weighted = weighted.add(self.snake_pattern, SnakePattern::default());
+        }
+        weighted
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Options {
+    strategy: Strategy,
+    games: usize,
+    seed: u64,
+    threads: usize,
+    replays: Option<PathBuf>,
+    order: [Directions; 4],
+    depth: usize,
+    probability_cutoff: f64,
+    weights: Weights,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            strategy: Strategy::Expectimax,
+            games: 100,
+            seed: 0,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            replays: None,
+            order: [
+                Directions::Down,
+                Directions::Left,
+                Directions::Right,
+                Directions::Up,
+            ],
+            depth: 2,
+            probability_cutoff: 0.0001,
+            weights: Weights {
+                empty_squares: 2.7,
+                monotonicity: 1.0,
+                smoothness: 0.1,
+                merge_potential: 0.7,
+                max_in_corner: 1.0,
+                snake_pattern: 0.0,
+            },
+        }
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value {} for {}", value, flag))
+}
+
+fn parse_order(value: &str) -> Result<[Directions; 4], String> {
+    let directions: Vec<Directions> = value
+        .chars()
+        .map(|letter| match letter.to_ascii_uppercase() {
+            'U' => Ok(Directions::Up),
+            'R' => Ok(Directions::Right),
+            'D' => Ok(Directions::Down),
+            'L' => Ok(Directions::Left),
+            _ => Err(format!("Invalid direction {} in --order", letter)),
+        })
+        .collect::<Result<_, _>>()?;
+    let mut order = [Directions::Up; 4];
+    if directions.len() != 4 || (1..4).any(|i| directions[..i].contains(&directions[i])) {
+        return Err("--order needs each of U, R, D and L once".to_string());
+    }
+    order.copy_from_slice(&directions);
+    Ok(order)
+}
+
+/// Parse the arguments after the program name, `None` if help was asked for
+fn parse_options<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            return Ok(None);
+        }
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), value.to_string()),
+            None => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("Missing value for {}", arg))?;
+                (arg, value)
+            }
+        };
+
+        match flag.as_str() {
+            "--agent" => {
+                options.strategy = match value.as_str() {
+                    "random" => Strategy::Random,
+                    "corner" => Strategy::Corner,
+                    "greedy" => Strategy::Greedy,
+                    "expectimax" => Strategy::Expectimax,
+                    _ => return Err(format!("Unknown agent {}", value)),
+                }
+            }
+            "--games" => options.games = parse_value(&flag, &value)?,
+            "--seed" => options.seed = parse_value(&flag, &value)?,
+            "--threads" => options.threads = parse_value(&flag, &value)?,
+            "--replays" => options.replays = Some(PathBuf::from(value)),
+            "--order" => options.order = parse_order(&value)?,
+            "--depth" => {
+                options.depth = parse_value(&flag, &value)?;
+                if options.depth == 0 {
+                    return Err("--depth must be at least 1".to_string());
+                }
+            }
+            "--probability-cutoff" => options.probability_cutoff = parse_value(&flag, &value)?,
+            "--empty-squares" => options.weights.empty_squares = parse_value(&flag, &value)?,
+            "--monotonicity" => options.weights.monotonicity = parse_value(&flag, &value)?,
+            "--smoothness" => options.weights.smoothness = parse_value(&flag, &value)?,
+            "--merge-potential" => options.weights.merge_potential = parse_value(&flag, &value)?,
+            "--max-in-corner" => options.weights.max_in_corner = parse_value(&flag, &value)?,
+            "--snake-pattern" => options.weights.snake_pattern = parse_value(&flag, &value)?,
+            _ => return Err(format!("Unknown argument {}", flag)),
+        }
+    }
+    Ok(Some(options))
+}
+
+/// Play and record every game once, writing its replay and building the report from the replays
+fn record_games<A, F>(options: &Options, directory: &Path, make_agent: &F) -> io::Result<Report>
+where
+    A: Agent<ArrayModel>,
+    F: Fn(u64) -> A + Sync,
+{
+    let start = Instant::now();
+    fs::create_dir_all(directory)?;
+    let seeds: Vec<u64> = (0..options.games as u64)
+        .map(|i| options.seed.wrapping_add(i))
+        .collect();
+    let chunk = seeds.len().div_ceil(options.threads.max(1)).max(1);
+
+    let mut games = thread::scope(|scope| {
+        let handles: Vec<_> = seeds
+            .chunks(chunk)
+            .map(|seeds| {
+                scope.spawn(move || -> io::Result<Vec<GameResult>> {
+                    let mut games = Vec::with_capacity(seeds.len());
+                    for seed in seeds {
+                        let replay = Replay::record::<ArrayModel, _>(&mut make_agent(*seed), *seed);
+                        let path = directory.join(format!("{}.replay", seed));
+                        let mut file = BufWriter::new(File::create(path)?);
+                        replay.write(&mut file)?;
+                        file.flush()?;
+                        games.push(game_result(*seed, &replay));
+                    }
+                    Ok(games)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("A game thread panicked"))
+            .collect::<io::Result<Vec<_>>>()
+    })?
+    .concat();
+    games.sort_by_key(|game| game.seed);
+    Ok(Report::new(games, start.elapsed()))
+}
+
+/// The result of a recorded game, the final board comes from replaying it
+fn game_result(seed: u64, replay: &Replay) -> GameResult {
+    let game = replay
+        .simulate::<ArrayModel>()
+        .expect("A recorded game replays");
+    GameResult {
+        seed,
+        score: replay.score,
+        moves: replay.moves.len(),
+        max_exponent: game.as_array().iter().max().copied().unwrap_or(0),
+    }
+}
+
+fn run<A, F>(options: &Options, make_agent: F) -> io::Result<Report>
+where
+    A: Agent<ArrayModel>,
+    F: Fn(u64) -> A + Sync,
+{
+    match &options.replays {
+        Some(directory) => record_games(options, directory, &make_agent),
+        None => Ok(Evaluation::new(options.games)
+            .seed(options.seed)
+            .threads(options.threads)
+            .run::<ArrayModel, _, _>(&make_agent)),
+    }
+}
+
+fn main() {
+    let options = match parse_options(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let result = match options.strategy {
+        Strategy::Random => run(&options, |seed| {
+            RandomAgent::new(StdRng::seed_from_u64(seed))
+        }),
+        Strategy::Corner => run(&options, |_| PreferenceOrder::new(options.order)),
+        Strategy::Greedy => run(&options, |_| Greedy),
+        Strategy::Expectimax => run(&options, |_| {
+            Expectimax::new(options.weights.build())
+                .depth(options.depth)
+                .probability_cutoff(options.probability_cutoff)
+        }),
+    };
+
+    match result {
+        Ok(report) => print!("{}", report),
+        Err(error) => {
+            eprintln!("Could not write the replays: {}", error);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Options>, String> {
+        parse_options(args.iter().map(|arg| arg.to_string()))
+    }
+
+    mod parse_options {
+        use super::*;
+
+        #[test]
+        fn reads_flags_with_and_without_equals() {
+            let options = parse(&["--agent", "corner", "--games=5", "--order", "ldru"])
+                .unwrap()
+                .unwrap();
+
+            assert_eq!(options.strategy, Strategy::Corner);
+            assert_eq!(options.games, 5);
+            assert_eq!(
+                options.order,
+                [
+                    Directions::Left,
+                    Directions::Down,
+                    Directions::Right,
+                    Directions::Up
+                ]
+            );
+        }
+
+        #[test]
+        fn defaults_to_the_standard_weights() {
+            let options = parse(&[]).unwrap().unwrap();
+
+            assert_eq!(
+                options.weights.build().names(),
+                Weighted::standard().names()
+            );
+        }
+
+        #[test]
+        fn rejects_invalid_flags() {
+            for args in [
+                &["--agent", "human"][..],
+                &["--games", "-1"],
+                &["--order", "UUDL"],
+                &["--depth", "0"],
+                &["--seed"],
+                &["--speed", "1"],
+            ]
+            .iter()
+            {
+                assert!(parse(args).is_err(), "{:?} parsed", args);
+            }
+        }
+    }
+
+    mod record_games {
+        use super::*;
+
+        #[test]
+        fn reports_the_same_games_as_an_evaluation() {
+            let directory = env::temp_dir().join(format!("batch-replays-{}", process::id()));
+            let options = Options {
+                games: 4,
+                seed: u64::MAX - 1,
+                threads: 2,
+                ..Options::default()
+            };
+
+            let report = record_games(&options, &directory, &|_| Greedy).unwrap();
+            let evaluation = Evaluation::new(4)
+                .seed(u64::MAX - 1)
+                .run::<ArrayModel, _, _>(|_| Greedy);
+            let written = fs::read_dir(&directory).unwrap().count();
+            fs::remove_dir_all(&directory).unwrap();
+
+            assert_eq!(report.games(), evaluation.games());
+            assert_eq!(written, 4);
+        }
+    }
+}
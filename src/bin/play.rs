@@ -0,0 +1,228 @@
+//! Play 2048 in the terminal
+//!
+//! ```text
+//! play [--seed <seed>]
+//! ```
+//!
+//! Slide with the arrow keys or WASD, undo with `u` and quit with `q`. The
+//! seed of the game is shown so it can be played again with `--seed`.
+
+use std::env;
+use std::io::{self, Read, Write};
+use std::process::{self, Command, Stdio};
+
+use game_2048_model::environment::Environment;
+use game_2048_model::models::{ArrayModel, Directions};
+
+#[path = "shared/args.rs"]
+mod args;
+
+use args::parse_seed;
+
+const USAGE: &str = "Usage: play [--seed <seed>]";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Key {
+    Slide(Directions),
+    Undo,
+    Quit,
+}
+
+/// Read the next key, arrow keys arrive as the escape sequences `ESC [ A` to `ESC [ D`
+fn next_key<I: Iterator<Item = u8>>(bytes: &mut I) -> Option<Key> {
+    loop {
+        let key = match bytes.next()? {
+            b'w' | b'W' => Key::Slide(Directions::Up),
+            b'd' | b'D' => Key::Slide(Directions::Right),
+            b's' | b'S' => Key::Slide(Directions::Down),
+            b'a' | b'A' => Key::Slide(Directions::Left),
+            b'u' | b'U' => Key::Undo,
+            // Ctrl-C does not interrupt in raw mode
+            b'q' | b'Q' | 3 => Key::Quit,
+            0x1b => {
+                if bytes.next()? != b'[' {
+                    continue;
+                }
+                match bytes.next()? {
+                    b'A' => Key::Slide(Directions::Up),
+                    b'C' => Key::Slide(Directions::Right),
+                    b'B' => Key::Slide(Directions::Down),
+                    b'D' => Key::Slide(Directions::Left),
+                    _ => continue,
+                }
+            }
+            _ => continue,
+        };
+        return Some(key);
+    }
+}
+
+fn render(environment: &Environment<ArrayModel>, seed: u64, undos: usize) -> String {
+    let board = environment.observation();
+    let best = board.iter().max().copied().unwrap_or(0);
+    let line = "+------+------+------+------+";
+
+    let mut lines = vec![
+        format!("2048  seed {}", seed),
+        format!(
+            "Score: {}  Best tile: {}",
+            environment.score(),
+            if best == 0 { 0 } else { 1u64 << best }
+        ),
+        String::new(),
+        line.to_string(),
+    ];
+    for row in board.chunks(4) {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|x| match x {
+                0 => "      ".to_string(),
+                x => format!("{:>5} ", 1u64 << x),
+            })
+            .collect();
+        lines.push(format!("|{}|", cells.join("|")));
+        lines.push(line.to_string());
+    }
+    lines.push(String::new());
+    if environment.is_done() {
+        lines.push("Game over! u to undo, q to quit".to_string());
+    } else {
+        lines.push(format!(
+            "Arrows or WASD to slide, u to undo ({} left), q to quit",
+            undos
+        ));
+    }
+    // Raw mode does not return the cursor on a new line
+    lines.join("\r\n") + "\r\n"
+}
+
+/// Puts the terminal in raw mode while alive, so keys are read without enter
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> Option<Self> {
+        let output = Command::new("stty")
+            .arg("-g")
+            .stdin(Stdio::inherit())
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let saved = String::from_utf8(output.stdout).ok()?.trim().to_string();
+        let status = Command::new("stty")
+            .args(["raw", "-echo"])
+            .stdin(Stdio::inherit())
+            .status()
+            .ok()?;
+        if status.success() {
+            Some(RawMode { saved })
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = Command::new("stty")
+            .arg(&self.saved)
+            .stdin(Stdio::inherit())
+            .status();
+    }
+}
+
+fn main() {
+    let seed = match parse_seed(env::args()) {
+        Ok(seed) => seed.unwrap_or_else(rand::random),
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let mut environment = Environment::<ArrayModel>::new();
+    environment.reset(seed);
+    let mut history: Vec<Environment<ArrayModel>> = Vec::new();
+
+    let stdin = io::stdin();
+    let raw_mode = RawMode::enable();
+    let mut bytes = stdin.lock().bytes().map_while(Result::ok);
+    let mut stdout = io::stdout();
+
+    loop {
+        // Clear the screen and move the cursor to the top left corner
+        let _ = write!(
+            stdout,
+            "\x1b[2J\x1b[H{}",
+            render(&environment, seed, history.len())
+        );
+        let _ = stdout.flush();
+
+        match next_key(&mut bytes) {
+            Some(Key::Slide(direction)) => {
+                let before = environment.clone();
+                if !environment.step(direction).info.invalid_move {
+                    history.push(before);
+                }
+            }
+            Some(Key::Undo) => {
+                if let Some(previous) = history.pop() {
+                    environment = previous;
+                }
+            }
+            Some(Key::Quit) | None => break,
+        }
+    }
+
+    drop(raw_mode);
+    println!("Final score {} with seed {}", environment.score(), seed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod next_key {
+        use super::*;
+
+        #[test]
+        fn reads_arrows_and_letters() {
+            let mut bytes = b"\x1b[Aa\x1b[Cxsu\x1b[Dq".iter().copied();
+            let keys: Vec<Key> = std::iter::from_fn(|| next_key(&mut bytes)).collect();
+
+            assert_eq!(
+                keys,
+                vec![
+                    Key::Slide(Directions::Up),
+                    Key::Slide(Directions::Left),
+                    Key::Slide(Directions::Right),
+                    Key::Slide(Directions::Down),
+                    Key::Undo,
+                    Key::Slide(Directions::Left),
+                    Key::Quit,
+                ]
+            );
+        }
+    }
+
+    mod render {
+        use super::*;
+
+        #[test]
+        fn shows_score_and_best_tile() {
+            let mut environment = Environment::<ArrayModel>::new();
+            environment.reset(3);
+            let screen = render(&environment, 3, 0);
+
+            assert!(screen.contains("seed 3"));
+            assert!(screen.contains("Score: 0"));
+            assert_eq!(
+                screen.lines().filter(|line| line.starts_with('+')).count(),
+                5
+            );
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Serve games over HTTP as JSON, built with the `server` feature
+//!
+//! ```text
+//! server [--host 127.0.0.1] [--port 8048] [--allow-origin http://localhost:3000]
+//! ```
+//!
+//! The routes are described on [`Sessions`]. Browsers may only call the server
+//! from the origin given with `--allow-origin`, so a frontend served from
+//! another local port can use it but other websites cannot. Request bodies
+//! larger than 4 KiB are rejected.
+
+use std::env;
+use std::io::Read;
+use std::process;
+
+use game_2048_model::server::Sessions;
+use tiny_http::{Header, Method, Response, Server};
+
+const USAGE: &str = "Usage: server [--host <address>] [--port <port>] [--allow-origin <origin>]";
+
+/// The largest request body read, the JSON of every route is much smaller
+const MAX_BODY: usize = 4 * 1024;
+
+#[derive(Debug, Clone)]
+struct Options {
+    host: String,
+    port: u16,
+    /// The origin sent in `Access-Control-Allow-Origin`, no CORS headers without one
+    allow_origin: Option<String>,
+    /// The CORS headers added to every response, checked once when parsing
+    cors: Vec<Header>,
+}
+
+fn parse_options<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options {
+        host: "127.0.0.1".to_string(),
+        port: 8048,
+        allow_origin: None,
+        cors: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), value.to_string()),
+            None => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("Missing value for {}", arg))?;
+                (arg, value)
+            }
+        };
+        match flag.as_str() {
+            "--host" => options.host = value,
+            "--port" => {
+                options.port = value
+                    .parse()
+                    .map_err(|_| format!("Invalid port {}", value))?
+            }
+            "--allow-origin" => {
+                options.cors = cors_headers(&value)?;
+                options.allow_origin = Some(value);
+            }
+            _ => return Err(format!("Unknown argument {}", flag)),
+        }
+    }
+    Ok(options)
+}
+
+/// The headers allowing a browser on the origin to call the server
+fn cors_headers(origin: &str) -> Result<Vec<Header>, String> {
+    // Printable ASCII without spaces, so the value can not break the header line
+    if origin.is_empty() || !origin.bytes().all(|byte| byte.is_ascii_graphic()) {
+        return Err(format!("Invalid origin {}", origin));
+    }
+    [
+        ("Access-Control-Allow-Origin", origin),
+        ("Access-Control-Allow-Methods", "GET, POST, DELETE, OPTIONS"),
+        ("Access-Control-Allow-Headers", "Content-Type"),
+        ("Vary", "Origin"),
+    ]
+    .iter()
+    .map(|(name, value)| {
+        Header::from_bytes(name.as_bytes(), value.as_bytes())
+            .map_err(|_| format!("Invalid origin {}", origin))
+    })
+    .collect()
+}
+
+/// Read the body of a request, or the status code to reject it with
+///
+/// Bodies longer than [`MAX_BODY`] get 413 and bodies that are not UTF-8 get 400.
+fn read_body<R: Read>(reader: R, length: Option<usize>) -> Result<String, u16> {
+    if length.is_some_and(|length| length > MAX_BODY) {
+        return Err(413);
+    }
+    let mut body = String::new();
+    reader
+        .take(MAX_BODY as u64 + 1)
+        .read_to_string(&mut body)
+        .map_err(|_| 400u16)?;
+    if body.len() > MAX_BODY {
+        return Err(413);
+    }
+    Ok(body)
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("Headers are ASCII")
+}
+
+fn main() {
+    let options = match parse_options(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    let (host, port) = (options.host.as_str(), options.port);
+    let server = match Server::http((host, port)) {
+        Ok(server) => server,
+        Err(error) => {
+            eprintln!("Could not listen on {}:{}: {}", host, port, error);
+            process::exit(1);
+        }
+    };
+    println!("Listening on http://{}:{}", host, port);
+
+    let mut sessions = Sessions::new();
+    for mut request in server.incoming_requests() {
+        let mut response = if *request.method() == Method::Options {
+            // Preflight request of a browser
+            Response::from_string("").with_status_code(204)
+        } else {
+            let length = request.body_length();
+            match read_body(request.as_reader(), length) {
+                Ok(body) => {
+                    let answer = sessions.handle(request.method().as_str(), request.url(), &body);
+                    Response::from_string(answer.body)
+                        .with_status_code(answer.status)
+                        .with_header(header("Content-Type", "application/json"))
+                }
+                Err(status) => Response::from_string("").with_status_code(status),
+            }
+        };
+        for header in options.cors.iter() {
+            response.add_header(header.clone());
+        }
+        let _ = request.respond(response);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_options(args.iter().map(|arg| arg.to_string()))
+    }
+
+    mod parse_options {
+        use super::*;
+
+        #[test]
+        fn allows_no_origin_by_default() {
+            let options = parse(&[]).unwrap();
+
+            assert_eq!(options.host, "127.0.0.1");
+            assert_eq!(options.allow_origin, None);
+            assert!(options.cors.is_empty());
+        }
+
+        #[test]
+        fn reads_the_allowed_origin() {
+            let options =
+                parse(&["--allow-origin=http://localhost:3000", "--port", "9000"]).unwrap();
+
+            assert_eq!(options.port, 9000);
+            assert_eq!(
+                options.allow_origin.as_deref(),
+                Some("http://localhost:3000")
+            );
+        }
+    }
+
+    mod cors_headers {
+        use super::*;
+
+        #[test]
+        fn allows_the_origin() {
+            let headers = cors_headers("http://localhost:3000").unwrap();
+
+            assert_eq!(headers[0].value.as_str(), "http://localhost:3000");
+        }
+
+        #[test]
+        fn rejects_origins_that_are_not_a_header_value() {
+            for origin in ["", "http://bücher.example", "a\r\nSet-Cookie: x", "a b"].iter() {
+                assert!(cors_headers(origin).is_err(), "{:?} accepted", origin);
+                assert!(parse(&["--allow-origin", origin]).is_err());
+            }
+        }
+    }
+
+    mod read_body {
+        use super::*;
+
+        #[test]
+        fn rejects_bodies_over_the_limit() {
+            assert_eq!(read_body(&b"{}"[..], Some(2)), Ok("{}".to_string()));
+            assert!(read_body(&vec![b' '; MAX_BODY][..], None).is_ok());
+            assert_eq!(read_body(&vec![b' '; MAX_BODY + 1][..], None), Err(413));
+            assert_eq!(read_body(&b""[..], Some(MAX_BODY + 1)), Err(413));
+        }
+
+        #[test]
+        fn rejects_bodies_that_are_not_text() {
+            assert_eq!(read_body(&[0xff, 0xfe][..], None), Err(400));
+        }
+    }
+}
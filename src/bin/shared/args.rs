@@ -0,0 +1,53 @@
+//! Command line parsing shared by the interactive binaries
+
+/// Read the optional `--seed <seed>` or `--seed=<seed>` flag, skipping the program name
+pub fn parse_seed<I: Iterator<Item = String>>(mut args: I) -> Result<Option<u64>, String> {
+    args.next();
+    let mut seed = None;
+    while let Some(arg) = args.next() {
+        let value = match arg.as_str() {
+            "--seed" => args.next().ok_or("Missing value for --seed")?,
+            _ => match arg.strip_prefix("--seed=") {
+                Some(value) => value.to_string(),
+                None => return Err(format!("Unknown argument {}", arg)),
+            },
+        };
+        seed = Some(
+            value
+                .parse()
+                .map_err(|_| format!("Invalid seed {}", value))?,
+        );
+    }
+    Ok(seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod parse_seed {
+        use super::*;
+
+        fn parse(args: &[&str]) -> Result<Option<u64>, String> {
+            parse_seed(
+                std::iter::once("play")
+                    .chain(args.iter().copied())
+                    .map(String::from),
+            )
+        }
+
+        #[test]
+        fn reads_both_forms() {
+            assert_eq!(parse(&[]), Ok(None));
+            assert_eq!(parse(&["--seed", "7"]), Ok(Some(7)));
+            assert_eq!(parse(&["--seed=8"]), Ok(Some(8)));
+        }
+
+        #[test]
+        fn rejects_invalid_arguments() {
+            assert!(parse(&["--seed"]).is_err());
+            assert!(parse(&["--seed", "x"]).is_err());
+            assert!(parse(&["--depth", "3"]).is_err());
+        }
+    }
+}
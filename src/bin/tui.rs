@@ -0,0 +1,75 @@
+//! Play 2048 full screen in the terminal, built with the `tui` feature
+//!
+//! ```text
+//! tui [--seed <seed>]
+//! ```
+
+use std::env;
+use std::io;
+use std::process;
+use std::time::Duration;
+
+use game_2048_model::models::Directions;
+use game_2048_model::tui::App;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::DefaultTerminal;
+
+#[path = "shared/args.rs"]
+mod args;
+
+use args::parse_seed;
+
+const USAGE: &str = "Usage: tui [--seed <seed>]";
+
+/// Time between two frames of an animation
+const FRAME: Duration = Duration::from_millis(16);
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| frame.render_widget(&*app, frame.area()))?;
+
+        if app.is_animating() && !event::poll(FRAME)? {
+            app.tick();
+            continue;
+        }
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        match key.code {
+            KeyCode::Up | KeyCode::Char('w') => app.slide(Directions::Up),
+            KeyCode::Right | KeyCode::Char('d') => app.slide(Directions::Right),
+            KeyCode::Down | KeyCode::Char('s') => app.slide(Directions::Down),
+            KeyCode::Left | KeyCode::Char('a') => app.slide(Directions::Left),
+            KeyCode::Char(' ') => app.play_hint(),
+            KeyCode::Char('n') => {
+                app.restart(rand::random());
+                true
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            _ => false,
+        };
+    }
+}
+
+fn main() -> io::Result<()> {
+    let seed = match parse_seed(env::args()) {
+        Ok(seed) => seed.unwrap_or_else(rand::random),
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let mut app = App::new(seed);
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+
+    println!(
+        "Final score {} with seed {}",
+        app.game().score(),
+        app.seed()
+    );
+    result
+}
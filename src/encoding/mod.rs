@@ -0,0 +1,226 @@
+//! Neural network input encodings of the board
+//!
+//! Every encoder writes into a buffer given by the caller and never allocates,
+//! so the same buffer can be reused for every board of a training run.
+//!
+//! ```
+//! use game_2048_model::encoding::{self, PLANES};
+//! use game_2048_model::models::{Model, Matrix};
+//!
+//! let game = Matrix::from([
+//!     [0, 0, 0, 0],
+//!     [4, 0, 0, 2],
+//!     [2, 8, 4, 0],
+//!     [3, 8, 6, 4]
+//! ]);
+//!
+//! let mut planes = [0.0; PLANES * 16];
+//! let mut mask = [0; 4];
+//! encoding::one_hot(&game.as_array(), &mut planes);
+//! encoding::legal_mask(&game, &mut mask);
+//! ```
+
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use crate::base::*;
+
+const SQUARES: usize = BOARD_SIZE * BOARD_SIZE;
+
+/// Number of one-hot planes per board, one for every exponent from 0 (empty) to 15
+pub const PLANES: usize = 16;
+
+/// The largest exponent that can be reached on the board, 2^17 = 131072
+pub const MAX_EXPONENT: BoardElement = 17;
+
+/// Write the board as one-hot planes of 16 squares each
+///
+/// Plane `p` has a 1 on the squares holding exponent `p`, plane 0 marks the
+/// empty squares and exponents above 15 are counted as 15. The planes are
+/// written one after the other, each in array form, giving a 16x4x4 tensor.
+///
+/// # Panics
+///
+/// Panics if `out` does not hold exactly `PLANES * 16` values.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::encoding::{one_hot, PLANES};
+///
+/// let board = [
+///     1,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,3
+/// ];
+/// let mut planes = [0.0; PLANES * 16];
+/// one_hot(&board, &mut planes);
+///
+/// assert_eq!(planes[1 * 16 + 0], 1.0);
+/// assert_eq!(planes[3 * 16 + 15], 1.0);
+/// assert_eq!(planes[0 * 16 + 1], 1.0);
+/// ```
+///
+pub fn one_hot(board: &ArrayBoard, out: &mut [f32]) {
+    assert_eq!(out.len(), PLANES * SQUARES, "Expected PLANES * 16 values");
+    for value in out.iter_mut() {
+        *value = 0.0;
+    }
+    for (square, exponent) in board.iter().enumerate() {
+        let plane = usize::from(*exponent).min(PLANES - 1);
+        out[plane * SQUARES + square] = 1.0;
+    }
+}
+
+/// Write the exponents of the board scaled to between 0 and 1
+///
+/// The exponents are the base 2 logarithm of the numbers and are divided by
+/// [`MAX_EXPONENT`], empty squares are 0.
+///
+/// # Panics
+///
+/// Panics if `out` does not hold exactly 16 values.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::encoding::{normalized, MAX_EXPONENT};
+///
+/// let board = [
+///     0,1,0,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,17
+/// ];
+/// let mut values = [0.0; 16];
+/// normalized(&board, &mut values);
+///
+/// assert_eq!(values[0], 0.0);
+/// assert_eq!(values[1], 1.0 / MAX_EXPONENT as f32);
+/// assert_eq!(values[15], 1.0);
+/// ```
+///
+pub fn normalized(board: &ArrayBoard, out: &mut [f32]) {
+    assert_eq!(out.len(), SQUARES, "Expected 16 values");
+    for (value, exponent) in out.iter_mut().zip(board.iter()) {
+        *value = f32::from(*exponent) / f32::from(MAX_EXPONENT);
+    }
+}
+
+/// Write which directions change the board, 1 for legal and 0 for illegal, in the order of [`DIRECTIONS`]
+///
+/// # Panics
+///
+/// Panics if `out` does not hold exactly 4 values.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::encoding::legal_mask;
+/// use game_2048_model::models::ArrayModel;
+///
+/// let game = ArrayModel::from([
+///     1,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0
+/// ]);
+/// let mut mask = [0; 4];
+/// legal_mask(&game, &mut mask);
+///
+/// assert_eq!(mask, [0, 1, 1, 0]);
+/// ```
+///
+pub fn legal_mask<M: Model>(game: &M, out: &mut [u8]) {
+    assert_eq!(out.len(), 4, "Expected 4 values");
+    let board = game.as_array();
+    for (legal, direction) in out.iter_mut().zip(DIRECTIONS.iter()) {
+        *legal = u8::from(M::from(board).slide(*direction).is_some());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{legal_mask, normalized, one_hot, PLANES};
+    use crate::models::{ArrayModel, Matrix};
+
+    mod one_hot {
+        use super::{one_hot, PLANES};
+
+        #[test]
+        fn one_plane_set_per_square() {
+            let board = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+            let mut planes = [0.0; PLANES * 16];
+            one_hot(&board, &mut planes);
+
+            for square in 0..16 {
+                let set: Vec<usize> = (0..PLANES)
+                    .filter(|plane| planes[plane * 16 + square] == 1.0)
+                    .collect();
+                assert_eq!(set, vec![square]);
+            }
+        }
+
+        #[test]
+        fn clears_previous_values() {
+            let mut planes = [1.0; PLANES * 16];
+            one_hot(&[0; 16], &mut planes);
+
+            assert_eq!(planes.iter().sum::<f32>(), 16.0);
+        }
+
+        #[test]
+        fn caps_large_exponents() {
+            let mut planes = [0.0; PLANES * 16];
+            one_hot(&[17; 16], &mut planes);
+
+            assert_eq!(planes[(PLANES - 1) * 16..].iter().sum::<f32>(), 16.0);
+        }
+
+        #[test]
+        #[should_panic]
+        fn panics_on_wrong_size() {
+            one_hot(&[0; 16], &mut [0.0; 16]);
+        }
+    }
+
+    mod normalized {
+        use super::normalized;
+
+        #[test]
+        fn empty_board_is_zero() {
+            let mut values = [1.0; 16];
+            normalized(&[0; 16], &mut values);
+
+            assert_eq!(values, [0.0; 16]);
+        }
+    }
+
+    mod legal_mask {
+        use super::{legal_mask, ArrayModel, Matrix};
+
+        #[rustfmt::skip]
+        #[test]
+        fn full_board_without_merges_has_no_legal_moves() {
+            let board = [
+                1,2,1,2,
+                2,1,2,1,
+                1,2,1,2,
+                2,1,2,1
+            ];
+            let mut mask = [1; 4];
+            legal_mask(&Matrix::from(board), &mut mask);
+
+            assert_eq!(mask, [0, 0, 0, 0]);
+        }
+
+        #[test]
+        fn empty_board_has_no_legal_moves() {
+            let mut mask = [1; 4];
+            legal_mask(&ArrayModel::from([0; 16]), &mut mask);
+
+            assert_eq!(mask, [0, 0, 0, 0]);
+        }
+    }
+}
@@ -0,0 +1,552 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+use super::RewardShaping;
+use crate::base::*;
+use crate::encoding::{self, PLANES};
+
+const SQUARES: usize = BOARD_SIZE * BOARD_SIZE;
+
+/// The squares of every line in the order a slide moves numbers, indexed like [`DIRECTIONS`]
+#[rustfmt::skip]
+const LINES: [[[usize; BOARD_SIZE]; BOARD_SIZE]; 4] = [
+    [[0, 4, 8, 12], [1, 5, 9, 13], [2, 6, 10, 14], [3, 7, 11, 15]],
+    [[3, 2, 1, 0], [7, 6, 5, 4], [11, 10, 9, 8], [15, 14, 13, 12]],
+    [[12, 8, 4, 0], [13, 9, 5, 1], [14, 10, 6, 2], [15, 11, 7, 3]],
+    [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11], [12, 13, 14, 15]],
+];
+
+/// Slide and merge a single line towards its first square with [`merge_line`]
+///
+/// Returns the new line together with the score and the sum of the merged exponents.
+fn slide_line(line: [BoardElement; BOARD_SIZE]) -> ([BoardElement; BOARD_SIZE], u64, f64) {
+    let mut result = line;
+    let mut score = 0;
+    let mut merge_log = 0.0;
+    merge_line(&mut result, |exponent| {
+        score += 1 << exponent;
+        merge_log += f64::from(exponent);
+    });
+    (result, score, merge_log)
+}
+
+/// Many games stepped in lockstep
+///
+/// The boards are stored as a structure of arrays, square by square, and are
+/// slid directly without going through a [`Model`]. Every game spawns numbers
+/// exactly like [`Model::random`] with its own generator, so game `i` of a
+/// batch plays out like an [`Environment`](super::Environment) reset with the
+/// same seed.
+///
+/// Games that end are reset automatically with the next unused seed, the
+/// step reports them as done together with their final score.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::environment::BatchEnvironment;
+/// use game_2048_model::models::Directions;
+///
+/// let mut batch = BatchEnvironment::new(1000, 42);
+/// let actions = vec![Directions::Left; 1000];
+/// let step = batch.step(&actions);
+///
+/// let mut observations = vec![0; 1000 * 16];
+/// batch.write_exponents(&mut observations);
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct BatchEnvironment {
+    len: usize,
+    squares: Vec<BoardElement>,
+    rngs: Vec<StdRng>,
+    seeds: Vec<u64>,
+    next_seed: u64,
+    scores: Vec<u64>,
+    moves: Vec<usize>,
+    shaping: RewardShaping,
+    rewards: Vec<f64>,
+    dones: Vec<bool>,
+    invalid_moves: Vec<bool>,
+    final_scores: Vec<u64>,
+}
+
+/// The result of [`BatchEnvironment::step`], one entry per game
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BatchStep<'a> {
+    /// The shaped reward of every game
+    pub rewards: &'a [f64],
+    /// True for the games that ended in this step and have been reset
+    pub dones: &'a [bool],
+    /// True for the games where the direction did not change the board
+    pub invalid_moves: &'a [bool],
+    /// The final score of the games that ended in this step, 0 for the others
+    pub final_scores: &'a [u64],
+}
+
+impl BatchEnvironment {
+    /// Create `len` games, game `i` starting from seed `seed + i`, wrapping around after `u64::MAX`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::BatchEnvironment;
+    ///
+    /// let batch = BatchEnvironment::new(8, 0);
+    ///
+    /// assert_eq!(batch.len(), 8);
+    /// ```
+    ///
+    pub fn new(len: usize, seed: u64) -> Self {
+        let mut batch = BatchEnvironment {
+            len,
+            squares: vec![0; len * SQUARES],
+            rngs: (0..len).map(|_| StdRng::seed_from_u64(0)).collect(),
+            seeds: vec![0; len],
+            next_seed: seed,
+            scores: vec![0; len],
+            moves: vec![0; len],
+            shaping: RewardShaping::default(),
+            rewards: vec![0.0; len],
+            dones: vec![false; len],
+            invalid_moves: vec![false; len],
+            final_scores: vec![0; len],
+        };
+        for game in 0..len {
+            batch.reset_game(game);
+        }
+        batch
+    }
+
+    /// Set how rewards are computed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::{BatchEnvironment, RewardShaping};
+    ///
+    /// let batch = BatchEnvironment::new(8, 0).reward_shaping(RewardShaping {
+    ///     invalid_move: -1.0,
+    ///     ..RewardShaping::default()
+    /// });
+    /// ```
+    ///
+    pub fn reward_shaping(mut self, shaping: RewardShaping) -> Self {
+        self.shaping = shaping;
+        self
+    }
+
+    /// The number of games in the batch
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::BatchEnvironment;
+    ///
+    /// assert_eq!(BatchEnvironment::new(3, 0).len(), 3);
+    /// ```
+    ///
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// True if the batch holds no games
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::BatchEnvironment;
+    ///
+    /// assert!(BatchEnvironment::new(0, 0).is_empty());
+    /// ```
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The seed the current game in the slot was started from
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::BatchEnvironment;
+    ///
+    /// assert_eq!(BatchEnvironment::new(3, 10).seed(2), 12);
+    /// ```
+    ///
+    pub fn seed(&self, game: usize) -> u64 {
+        self.seeds[game]
+    }
+
+    /// The score of the current game in the slot
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::BatchEnvironment;
+    ///
+    /// assert_eq!(BatchEnvironment::new(3, 10).score(0), 0);
+    /// ```
+    ///
+    pub fn score(&self, game: usize) -> u64 {
+        self.scores[game]
+    }
+
+    /// The board of a single game in array form
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::BatchEnvironment;
+    ///
+    /// let batch = BatchEnvironment::new(3, 10);
+    ///
+    /// assert_eq!(batch.board(1).iter().filter(|x| **x != 0).count(), 2);
+    /// ```
+    ///
+    pub fn board(&self, game: usize) -> ArrayBoard {
+        let mut board = [0; SQUARES];
+        for (square, value) in board.iter_mut().enumerate() {
+            *value = self.squares[square * self.len + game];
+        }
+        board
+    }
+
+    fn reset_game(&mut self, game: usize) {
+        let seed = self.next_seed;
+        self.next_seed = self.next_seed.wrapping_add(1);
+        self.seeds[game] = seed;
+        self.rngs[game] = StdRng::seed_from_u64(seed);
+        for square in 0..SQUARES {
+            self.squares[square * self.len + game] = 0;
+        }
+        self.spawn(game);
+        self.spawn(game);
+        self.scores[game] = 0;
+        self.moves[game] = 0;
+    }
+
+    /// Spawn a number the same way as [`Model::random`]
+    fn spawn(&mut self, game: usize) {
+        let len = self.len;
+        let empty = (0..SQUARES)
+            .filter(|square| self.squares[square * len + game] == 0)
+            .count();
+        if empty == 0 {
+            return;
+        }
+        let squares = &mut self.squares;
+        let rng = &mut self.rngs[game];
+        let target: usize = rng.gen_range(0, empty);
+        let square = (0..SQUARES)
+            .filter(|square| squares[square * len + game] == 0)
+            .nth(target)
+            .expect("The target is one of the empty squares");
+        squares[square * len + game] = if rng.gen_range(0, 10) > 8 { 2 } else { 1 };
+    }
+
+    /// Slide one game, returning the score and merged exponents if the board changed
+    fn slide(&mut self, game: usize, direction: usize) -> Option<(u64, f64)> {
+        let len = self.len;
+        let mut changed = false;
+        let mut score = 0;
+        let mut merge_log = 0.0;
+        for line in LINES[direction].iter() {
+            let mut values = [0; BOARD_SIZE];
+            for (value, square) in values.iter_mut().zip(line.iter()) {
+                *value = self.squares[square * len + game];
+            }
+            let (result, line_score, line_merge_log) = slide_line(values);
+            if result != values {
+                changed = true;
+                score += line_score;
+                merge_log += line_merge_log;
+                for (value, square) in result.iter().zip(line.iter()) {
+                    self.squares[square * len + game] = *value;
+                }
+            }
+        }
+        if changed {
+            Some((score, merge_log))
+        } else {
+            None
+        }
+    }
+
+    /// True if the direction would change the board of the game
+    fn is_legal(&self, game: usize, direction: usize) -> bool {
+        LINES[direction].iter().any(|line| {
+            let mut values = [0; BOARD_SIZE];
+            for (value, square) in values.iter_mut().zip(line.iter()) {
+                *value = self.squares[square * self.len + game];
+            }
+            slide_line(values).0 != values
+        })
+    }
+
+    /// Slide every game towards its direction and spawn new numbers
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is not exactly one direction per game.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::BatchEnvironment;
+    /// use game_2048_model::models::Directions;
+    ///
+    /// let mut batch = BatchEnvironment::new(2, 0);
+    /// let step = batch.step(&[Directions::Up, Directions::Down]);
+    ///
+    /// assert_eq!(step.rewards.len(), 2);
+    /// ```
+    ///
+    pub fn step(&mut self, actions: &[Directions]) -> BatchStep<'_> {
+        assert_eq!(actions.len(), self.len, "Expected one direction per game");
+
+        for (game, action) in actions.iter().enumerate() {
+            let direction = DIRECTIONS
+                .iter()
+                .position(|direction| direction == action)
+                .expect("Every direction is in DIRECTIONS");
+            self.dones[game] = false;
+            self.final_scores[game] = 0;
+
+            match self.slide(game, direction) {
+                None => {
+                    self.invalid_moves[game] = true;
+                    self.rewards[game] = self.shaping.invalid_move;
+                }
+                Some((score, merge_log)) => {
+                    self.invalid_moves[game] = false;
+                    self.rewards[game] = self.shaping.score * score as f64
+                        + self.shaping.merge_log * merge_log
+                        + self.shaping.survival;
+                    self.scores[game] += score;
+                    self.moves[game] += 1;
+                    self.spawn(game);
+                }
+            }
+
+            if !(0..4).any(|direction| self.is_legal(game, direction)) {
+                self.dones[game] = true;
+                self.final_scores[game] = self.scores[game];
+                self.reset_game(game);
+            }
+        }
+
+        BatchStep {
+            rewards: &self.rewards,
+            dones: &self.dones,
+            invalid_moves: &self.invalid_moves,
+            final_scores: &self.final_scores,
+        }
+    }
+
+    /// Write which directions change each board, four values per game in the order of [`DIRECTIONS`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` does not hold exactly `4 * len` values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::BatchEnvironment;
+    ///
+    /// let batch = BatchEnvironment::new(2, 0);
+    /// let mut mask = [0; 8];
+    /// batch.write_legal_actions(&mut mask);
+    ///
+    /// assert!(mask.iter().any(|legal| *legal == 1));
+    /// ```
+    ///
+    pub fn write_legal_actions(&self, out: &mut [u8]) {
+        assert_eq!(out.len(), 4 * self.len, "Expected 4 values per game");
+        for (game, mask) in out.chunks_exact_mut(4).enumerate() {
+            for (direction, legal) in mask.iter_mut().enumerate() {
+                *legal = u8::from(self.is_legal(game, direction));
+            }
+        }
+    }
+
+    /// Write the exponents of every board, 16 consecutive values per game in array form
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` does not hold exactly `16 * len` values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::BatchEnvironment;
+    ///
+    /// let batch = BatchEnvironment::new(2, 0);
+    /// let mut exponents = [0; 32];
+    /// batch.write_exponents(&mut exponents);
+    ///
+    /// assert_eq!(exponents[16..], batch.board(1));
+    /// ```
+    ///
+    pub fn write_exponents(&self, out: &mut [BoardElement]) {
+        assert_eq!(out.len(), SQUARES * self.len, "Expected 16 values per game");
+        for (game, board) in out.chunks_exact_mut(SQUARES).enumerate() {
+            for (square, value) in board.iter_mut().enumerate() {
+                *value = self.squares[square * self.len + game];
+            }
+        }
+    }
+
+    /// Write every board as one-hot planes, see [`encoding::one_hot`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` does not hold exactly `PLANES * 16 * len` values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::encoding::PLANES;
+    /// use game_2048_model::environment::BatchEnvironment;
+    ///
+    /// let batch = BatchEnvironment::new(2, 0);
+    /// let mut planes = vec![0.0; 2 * PLANES * 16];
+    /// batch.write_one_hot(&mut planes);
+    ///
+    /// assert_eq!(planes.iter().sum::<f32>(), 32.0);
+    /// ```
+    ///
+    pub fn write_one_hot(&self, out: &mut [f32]) {
+        assert_eq!(
+            out.len(),
+            PLANES * SQUARES * self.len,
+            "Expected PLANES * 16 values per game"
+        );
+        for (game, planes) in out.chunks_exact_mut(PLANES * SQUARES).enumerate() {
+            encoding::one_hot(&self.board(game), planes);
+        }
+    }
+
+    /// Write every board as normalized exponents, see [`encoding::normalized`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` does not hold exactly `16 * len` values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::BatchEnvironment;
+    ///
+    /// let batch = BatchEnvironment::new(2, 0);
+    /// let mut values = [0.0; 32];
+    /// batch.write_normalized(&mut values);
+    ///
+    /// assert!(values.iter().all(|value| *value >= 0.0 && *value <= 1.0));
+    /// ```
+    ///
+    pub fn write_normalized(&self, out: &mut [f32]) {
+        assert_eq!(out.len(), SQUARES * self.len, "Expected 16 values per game");
+        for (game, values) in out.chunks_exact_mut(SQUARES).enumerate() {
+            encoding::normalized(&self.board(game), values);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slide_line, BatchEnvironment};
+    use crate::environment::Environment;
+    use crate::models::{ArrayModel, Directions, DIRECTIONS};
+
+    mod slide_line {
+        use super::slide_line;
+
+        #[test]
+        fn merges_each_pair_once() {
+            assert_eq!(slide_line([1, 1, 1, 1]), ([2, 2, 0, 0], 8, 4.0));
+        }
+
+        #[test]
+        fn merges_across_gaps() {
+            assert_eq!(slide_line([0, 3, 0, 3]), ([4, 0, 0, 0], 16, 4.0));
+        }
+
+        #[test]
+        fn does_not_merge_unequal_numbers() {
+            assert_eq!(slide_line([1, 3, 3, 0]), ([1, 4, 0, 0], 16, 4.0));
+        }
+    }
+
+    mod new {
+        use super::*;
+
+        #[test]
+        fn seeds_wrap_around() {
+            let batch = BatchEnvironment::new(3, u64::MAX - 1);
+
+            assert_eq!(batch.seed(1), u64::MAX);
+            assert_eq!(batch.seed(2), 0);
+        }
+    }
+
+    mod step {
+        use super::{ArrayModel, BatchEnvironment, Directions, Environment, DIRECTIONS};
+
+        #[test]
+        fn plays_like_the_environment() {
+            let mut batch = BatchEnvironment::new(4, 20);
+            let mut environments: Vec<Environment<ArrayModel>> = (0..4)
+                .map(|game| {
+                    let mut environment = Environment::new();
+                    environment.reset(20 + game);
+                    environment
+                })
+                .collect();
+
+            for turn in 0..50 {
+                let actions: Vec<Directions> =
+                    (0..4).map(|game| DIRECTIONS[(turn + game) % 4]).collect();
+                let step = batch.step(&actions);
+                if step.dones.iter().any(|done| *done) {
+                    break;
+                }
+                for (game, environment) in environments.iter_mut().enumerate() {
+                    let expected = environment.step(actions[game]);
+                    assert_eq!(step.rewards[game], expected.reward);
+                    assert_eq!(step.invalid_moves[game], expected.info.invalid_move);
+                }
+                for (game, environment) in environments.iter().enumerate() {
+                    assert_eq!(batch.board(game), environment.observation());
+                }
+            }
+        }
+
+        #[test]
+        fn resets_finished_games_with_the_next_seed() {
+            let mut batch = BatchEnvironment::new(2, 100);
+            let mut finished = 0;
+            for turn in 0..10_000 {
+                let actions = [DIRECTIONS[turn % 4], DIRECTIONS[(turn + 1) % 4]];
+                let step = batch.step(&actions);
+                for game in 0..2 {
+                    if step.dones[game] {
+                        assert!(step.final_scores[game] > 0);
+                        finished += 1;
+                    }
+                }
+                if finished >= 2 {
+                    break;
+                }
+            }
+
+            assert!(finished >= 2);
+            assert!(batch.seed(0) >= 102 || batch.seed(1) >= 102);
+            assert!(batch.board(0).iter().any(|x| *x != 0));
+        }
+    }
+}
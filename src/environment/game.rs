@@ -0,0 +1,462 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+use crate::base::*;
+
+/// The sum of the base 2 logarithm of every number created by the merges of a slide.
+///
+/// Like [`slide_score`], every number 2^n built only from merges has added a known
+/// amount, here 1.5 * 2^n - n - 2, so the difference between the boards gives the
+/// value of the merges.
+pub(crate) fn slide_merge_log(before: &ArrayBoard, after: &ArrayBoard) -> f64 {
+    fn merged_log(board: &ArrayBoard) -> f64 {
+        board
+            .iter()
+            .filter(|x| **x > 1)
+            .map(|x| 1.5 * 2f64.powi(i32::from(*x)) - f64::from(*x) - 2.0)
+            .sum()
+    }
+    merged_log(after) - merged_log(before)
+}
+
+/// How the reward of a step is computed
+///
+/// The reward is the sum of every term multiplied by its weight. By default
+/// only the score gained by the slide counts.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RewardShaping {
+    /// Weight of the score gained by the merges of the slide
+    pub score: f64,
+    /// Weight of the sum of the base 2 logarithm of every merged number
+    pub merge_log: f64,
+    /// Reward for every legal slide
+    pub survival: f64,
+    /// Reward for a direction that does not change the board, usually negative
+    pub invalid_move: f64,
+}
+
+impl Default for RewardShaping {
+    fn default() -> Self {
+        RewardShaping {
+            score: 1.0,
+            merge_log: 0.0,
+            survival: 0.0,
+            invalid_move: 0.0,
+        }
+    }
+}
+
+/// Extra information about a step
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Info {
+    /// The score gained by the slide
+    pub score_gained: u64,
+    /// The total score of the game so far
+    pub score: u64,
+    /// The number of legal slides played so far
+    pub moves: usize,
+    /// The largest exponent on the board
+    pub max_exponent: BoardElement,
+    /// True if the direction did not change the board
+    pub invalid_move: bool,
+}
+
+/// The result of [`Environment::step`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Step {
+    /// The board after the slide and spawn, in array form
+    pub observation: ArrayBoard,
+    /// The shaped reward of the step
+    pub reward: f64,
+    /// True when no direction can change the board any more
+    pub done: bool,
+    /// Extra information about the step
+    pub info: Info,
+}
+
+/// Reinforcement learning environment wrapping a game model
+///
+/// Every episode starts from a seed so it can be replayed exactly. A step
+/// slides the board, spawns a number with [`Model::random`] and reports the
+/// reward. A direction that does not change the board leaves the game as it
+/// is, no number is spawned and the step gets the `invalid_move` reward.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::environment::Environment;
+/// use game_2048_model::models::{ArrayModel, DIRECTIONS};
+///
+/// let mut environment = Environment::<ArrayModel>::new();
+/// environment.reset(42);
+///
+/// loop {
+///     let legal = environment.legal_actions();
+///     let action = (0..4).find(|i| legal[*i]).unwrap();
+///     if environment.step(DIRECTIONS[action]).done {
+///         break;
+///     }
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct Environment<M> {
+    game: M,
+    rng: StdRng,
+    shaping: RewardShaping,
+    score: u64,
+    moves: usize,
+}
+
+impl<M: Model> Environment<M> {
+    /// Create an environment with the default reward shaping, reset with seed 0
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::Environment;
+    /// use game_2048_model::models::Matrix;
+    ///
+    /// let environment = Environment::<Matrix>::new();
+    ///
+    /// assert_eq!(environment.observation().iter().filter(|x| **x != 0).count(), 2);
+    /// ```
+    ///
+    pub fn new() -> Self {
+        let mut environment = Environment {
+            game: M::new(),
+            rng: StdRng::seed_from_u64(0),
+            shaping: RewardShaping::default(),
+            score: 0,
+            moves: 0,
+        };
+        environment.reset(0);
+        environment
+    }
+
+    /// Set how rewards are computed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::{Environment, RewardShaping};
+    /// use game_2048_model::models::ArrayModel;
+    ///
+    /// let environment = Environment::<ArrayModel>::new().reward_shaping(RewardShaping {
+    ///     score: 0.0,
+    ///     survival: 1.0,
+    ///     invalid_move: -1.0,
+    ///     ..RewardShaping::default()
+    /// });
+    /// ```
+    ///
+    pub fn reward_shaping(mut self, shaping: RewardShaping) -> Self {
+        self.shaping = shaping;
+        self
+    }
+
+    /// Start a new game with two spawned numbers and return its board
+    ///
+    /// The same seed always gives the same game for the same directions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::Environment;
+    /// use game_2048_model::models::ArrayModel;
+    ///
+    /// let mut environment = Environment::<ArrayModel>::new();
+    ///
+    /// assert_eq!(environment.reset(7), environment.reset(7));
+    /// ```
+    ///
+    pub fn reset(&mut self, seed: u64) -> ArrayBoard {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.game = M::new();
+        let _ = self.game.random(&mut self.rng);
+        let _ = self.game.random(&mut self.rng);
+        self.score = 0;
+        self.moves = 0;
+        self.game.as_array()
+    }
+
+    /// Slide the board towards the direction and spawn a new number
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::Environment;
+    /// use game_2048_model::models::{ArrayModel, Directions};
+    ///
+    /// let mut environment = Environment::<ArrayModel>::new();
+    /// environment.reset(1);
+    /// let step = environment.step(Directions::Left);
+    ///
+    /// assert_eq!(step.observation, environment.observation());
+    /// ```
+    ///
+    pub fn step(&mut self, action: Directions) -> Step {
+        let before = self.game.as_array();
+        let invalid_move = self.game.slide(action).is_none();
+
+        let (score_gained, reward) = if invalid_move {
+            (0, self.shaping.invalid_move)
+        } else {
+            let after = self.game.as_array();
+            let score_gained = slide_score(&before, &after);
+            self.score += score_gained;
+            self.moves += 1;
+            let _ = self.game.random(&mut self.rng);
+            let reward = self.shaping.score * score_gained as f64
+                + self.shaping.merge_log * slide_merge_log(&before, &after)
+                + self.shaping.survival;
+            (score_gained, reward)
+        };
+
+        let observation = self.game.as_array();
+        Step {
+            observation,
+            reward,
+            done: self.is_done(),
+            info: Info {
+                score_gained,
+                score: self.score,
+                moves: self.moves,
+                max_exponent: observation.iter().max().copied().unwrap_or(0),
+                invalid_move,
+            },
+        }
+    }
+
+    /// Which directions change the board, in the order of [`DIRECTIONS`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::Environment;
+    /// use game_2048_model::models::ArrayModel;
+    ///
+    /// let environment = Environment::<ArrayModel>::new();
+    ///
+    /// assert!(environment.legal_actions().iter().any(|legal| *legal));
+    /// ```
+    ///
+    pub fn legal_actions(&self) -> [bool; 4] {
+        let board = self.game.as_array();
+        let mut legal = [false; 4];
+        for (legal, direction) in legal.iter_mut().zip(DIRECTIONS.iter()) {
+            *legal = M::from(board).slide(*direction).is_some();
+        }
+        legal
+    }
+
+    /// True when no direction can change the board
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::Environment;
+    /// use game_2048_model::models::ArrayModel;
+    ///
+    /// assert!(!Environment::<ArrayModel>::new().is_done());
+    /// ```
+    ///
+    pub fn is_done(&self) -> bool {
+        !self.legal_actions().iter().any(|legal| *legal)
+    }
+
+    /// The current board in array form
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::Environment;
+    /// use game_2048_model::models::ArrayModel;
+    ///
+    /// let mut environment = Environment::<ArrayModel>::new();
+    ///
+    /// assert_eq!(environment.reset(3), environment.observation());
+    /// ```
+    ///
+    pub fn observation(&self) -> ArrayBoard {
+        self.game.as_array()
+    }
+
+    /// The wrapped game model
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::Environment;
+    /// use game_2048_model::models::{ArrayModel, Model};
+    ///
+    /// let environment = Environment::<ArrayModel>::new();
+    ///
+    /// assert_eq!(environment.game().as_array(), environment.observation());
+    /// ```
+    ///
+    pub fn game(&self) -> &M {
+        &self.game
+    }
+
+    /// The total score of the current game
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::environment::Environment;
+    /// use game_2048_model::models::ArrayModel;
+    ///
+    /// assert_eq!(Environment::<ArrayModel>::new().score(), 0);
+    /// ```
+    ///
+    pub fn score(&self) -> u64 {
+        self.score
+    }
+}
+
+impl<M: Model> Default for Environment<M> {
+    fn default() -> Self {
+        Environment::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slide_merge_log, Environment, RewardShaping};
+    use crate::models::{ArrayModel, Directions, Matrix, DIRECTIONS};
+
+    mod slide_merge_log {
+        use super::slide_merge_log;
+
+        #[rustfmt::skip]
+        #[test]
+        fn sums_exponents_of_merged_numbers() {
+            let before = [
+                1,1,2,2,
+                3,3,0,0,
+                0,0,0,0,
+                0,0,0,0
+            ];
+            let after = [
+                2,3,0,0,
+                4,0,0,0,
+                0,0,0,0,
+                0,0,0,0
+            ];
+
+            assert_eq!(slide_merge_log(&before, &after), 2.0 + 3.0 + 4.0);
+        }
+    }
+
+    mod step {
+        use super::{Directions, Environment, RewardShaping, DIRECTIONS};
+        use crate::models::ArrayModel;
+
+        #[rustfmt::skip]
+        #[test]
+        fn invalid_move_keeps_the_board() {
+            let mut environment = Environment::<ArrayModel>::new().reward_shaping(RewardShaping {
+                invalid_move: -5.0,
+                ..RewardShaping::default()
+            });
+            // Every number is already against the left edge and none can merge
+            let before = [
+                1,0,0,0,
+                2,0,0,0,
+                0,0,0,0,
+                3,1,0,0
+            ];
+            environment.game = ArrayModel::from(before);
+            assert!(!environment.legal_actions()[3]);
+
+            let step = environment.step(Directions::Left);
+
+            assert_eq!(step.observation, before);
+            assert_eq!(step.reward, -5.0);
+            assert!(step.info.invalid_move);
+            assert_eq!(step.info.moves, 0);
+        }
+
+        #[test]
+        fn legal_move_spawns_a_number() {
+            let mut environment = Environment::<ArrayModel>::new();
+            environment.reset(11);
+            let legal = environment.legal_actions();
+            let action = (0..4).find(|i| legal[*i]).unwrap();
+            let count = |board: &[u8; 16]| board.iter().filter(|x| **x != 0).count();
+            let before = count(&environment.observation());
+
+            let step = environment.step(DIRECTIONS[action]);
+
+            assert!(!step.info.invalid_move);
+            assert_eq!(step.info.moves, 1);
+            assert!(count(&step.observation) <= before + 1);
+        }
+
+        #[test]
+        fn survival_reward_per_legal_move() {
+            let mut environment = Environment::<ArrayModel>::new().reward_shaping(RewardShaping {
+                score: 0.0,
+                survival: 1.0,
+                ..RewardShaping::default()
+            });
+            environment.reset(2);
+            let mut total = 0.0;
+            let mut moves = 0;
+            for direction in [
+                Directions::Up,
+                Directions::Left,
+                Directions::Down,
+                Directions::Right,
+            ]
+            .iter()
+            .cycle()
+            {
+                let step = environment.step(*direction);
+                total += step.reward;
+                moves = step.info.moves;
+                if step.done {
+                    break;
+                }
+            }
+
+            assert_eq!(total, moves as f64);
+        }
+
+        #[test]
+        fn score_reward_adds_up_to_score() {
+            let mut environment = Environment::<ArrayModel>::new();
+            environment.reset(5);
+            let mut total = 0.0;
+            for direction in DIRECTIONS.iter().cycle() {
+                let step = environment.step(*direction);
+                total += step.reward;
+                if step.done {
+                    break;
+                }
+            }
+
+            assert_eq!(total, environment.score() as f64);
+            assert!(environment.score() > 0);
+        }
+    }
+
+    mod reset {
+        use super::{ArrayModel, Environment, Matrix, DIRECTIONS};
+
+        #[test]
+        fn same_seed_same_game_for_all_models() {
+            let mut array = Environment::<ArrayModel>::new();
+            let mut matrix = Environment::<Matrix>::new();
+
+            assert_eq!(array.reset(9), matrix.reset(9));
+            for direction in DIRECTIONS.iter().take(3) {
+                assert_eq!(array.step(*direction), matrix.step(*direction));
+            }
+        }
+    }
+}
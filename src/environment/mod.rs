@@ -0,0 +1,16 @@
+//! Reinforcement learning environments
+//!
+//! ```
+//! use game_2048_model::environment::Environment;
+//! use game_2048_model::models::{ArrayModel, Directions};
+//!
+//! let mut environment = Environment::<ArrayModel>::new();
+//! let observation = environment.reset(42);
+//! let step = environment.step(Directions::Up);
+//! ```
+
+mod batch;
+mod game;
+
+pub use batch::{BatchEnvironment, BatchStep};
+pub use game::{Environment, Info, RewardShaping, Step};
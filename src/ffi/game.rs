@@ -0,0 +1,539 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use std::os::raw::c_char;
+use std::ptr;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::base::*;
+use crate::encoding::MAX_EXPONENT;
+use crate::models::ArrayModel;
+
+/// Slide towards the top
+pub const G2048_UP: u32 = 0;
+/// Slide towards the right
+pub const G2048_RIGHT: u32 = 1;
+/// Slide towards the bottom
+pub const G2048_DOWN: u32 = 2;
+/// Slide towards the left
+pub const G2048_LEFT: u32 = 3;
+/// The number of squares of a board, the size of the buffer given to [`g2048_board`]
+///
+/// A literal so that the generated header does not depend on [`BOARD_SIZE`].
+pub const G2048_SQUARES: usize = 16;
+
+/// The result of a call, every value but `Ok` means nothing changed
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::NoEmptyError;
+/// use game_2048_model::ffi::G2048Status;
+///
+/// assert_eq!(G2048Status::from(NoEmptyError), G2048Status::NoEmptySquare);
+/// assert_eq!(G2048Status::Ok as i32, 0);
+/// ```
+///
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum G2048Status {
+    /// The call succeeded
+    Ok = 0,
+    /// The slide does not change the board
+    Unchanged = 1,
+    /// There is no empty square to spawn a number on, see [`NoEmptyError`]
+    NoEmptySquare = 2,
+    /// A pointer argument was null
+    NullPointer = 3,
+    /// An argument is out of range, like a direction above 3 or a small buffer
+    InvalidArgument = 4,
+}
+
+impl From<NoEmptyError> for G2048Status {
+    fn from(_: NoEmptyError) -> Self {
+        G2048Status::NoEmptySquare
+    }
+}
+
+/// An opaque game handle, created by [`g2048_new`] and freed by [`g2048_free`]
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ffi::*;
+///
+/// let game = g2048_new(42);
+/// unsafe {
+///     assert_eq!(g2048_score(game), 0);
+///     g2048_free(game);
+/// }
+/// ```
+///
+pub struct G2048Game {
+    game: ArrayModel,
+    rng: StdRng,
+    score: u64,
+}
+
+impl G2048Game {
+    fn new(board: ArrayBoard, seed: u64) -> Self {
+        G2048Game {
+            game: ArrayModel::from(board),
+            rng: StdRng::seed_from_u64(seed),
+            score: 0,
+        }
+    }
+}
+
+/// Start a new game with two spawned numbers
+///
+/// Spawns come from a generator seeded here, so a seed always gives the same
+/// game. The handle must be freed with [`g2048_free`].
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ffi::*;
+///
+/// let game = g2048_new(7);
+/// let mut board = [0; G2048_SQUARES];
+/// unsafe {
+///     g2048_board(game, board.as_mut_ptr(), board.len());
+///     g2048_free(game);
+/// }
+/// assert_eq!(board.iter().filter(|x| **x != 0).count(), 2);
+/// ```
+///
+#[no_mangle]
+pub extern "C" fn g2048_new(seed: u64) -> *mut G2048Game {
+    let mut game = G2048Game::new([0; G2048_SQUARES], seed);
+    for _ in 0..2 {
+        game.game
+            .random(&mut game.rng)
+            .expect("An empty board has room for two numbers");
+    }
+    Box::into_raw(Box::new(game))
+}
+
+/// Load a game from the exponents of its numbers in array form
+///
+/// The board is read from [`G2048_SQUARES`] bytes, 1 is a 2, 2 is a 4 and 0 an
+/// empty square. Returns null if the board is null or has an exponent above
+/// 17. The handle must be freed with [`g2048_free`].
+///
+/// # Safety
+///
+/// `board` must be null or point to [`G2048_SQUARES`] readable bytes.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ffi::*;
+///
+/// let board = [1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+/// unsafe {
+///     let game = g2048_from_board(board.as_ptr(), 7);
+///     assert!(!game.is_null());
+///     g2048_free(game);
+/// }
+/// ```
+///
+#[no_mangle]
+pub unsafe extern "C" fn g2048_from_board(board: *const u8, seed: u64) -> *mut G2048Game {
+    if board.is_null() {
+        return ptr::null_mut();
+    }
+    let mut array: ArrayBoard = [0; G2048_SQUARES];
+    array.copy_from_slice(std::slice::from_raw_parts(board, G2048_SQUARES));
+    if array.iter().any(|x| *x > MAX_EXPONENT) {
+        return ptr::null_mut();
+    }
+    Box::into_raw(Box::new(G2048Game::new(array, seed)))
+}
+
+/// Free a game, does nothing if it is null
+///
+/// # Safety
+///
+/// `game` must be null or a handle that was not freed yet.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ffi::*;
+///
+/// unsafe {
+///     g2048_free(g2048_new(7));
+///     g2048_free(std::ptr::null_mut());
+/// }
+/// ```
+///
+#[no_mangle]
+pub unsafe extern "C" fn g2048_free(game: *mut G2048Game) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Slide towards a direction, [`G2048_UP`] to [`G2048_LEFT`]
+///
+/// Returns `Unchanged` if the board did not change. No number is spawned,
+/// call [`g2048_spawn`] after a successful slide.
+///
+/// # Safety
+///
+/// `game` must be null or a valid handle.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ffi::*;
+///
+/// let board = [1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+/// unsafe {
+///     let game = g2048_from_board(board.as_ptr(), 7);
+///     assert_eq!(g2048_slide(game, G2048_LEFT), G2048Status::Ok);
+///     assert_eq!(g2048_slide(game, G2048_LEFT), G2048Status::Unchanged);
+///     assert_eq!(g2048_slide(game, 4), G2048Status::InvalidArgument);
+///     assert_eq!(g2048_score(game), 4);
+///     g2048_free(game);
+/// }
+/// ```
+///
+#[no_mangle]
+pub unsafe extern "C" fn g2048_slide(game: *mut G2048Game, direction: u32) -> G2048Status {
+    let game = match game.as_mut() {
+        Some(game) => game,
+        None => return G2048Status::NullPointer,
+    };
+    let direction = match DIRECTIONS.get(direction as usize) {
+        Some(direction) => *direction,
+        None => return G2048Status::InvalidArgument,
+    };
+    let before = game.game.as_array();
+    if game.game.slide(direction).is_none() {
+        return G2048Status::Unchanged;
+    }
+    game.score += slide_score(&before, &game.game.as_array());
+    G2048Status::Ok
+}
+
+/// Spawn a 2 or a 4 on a random empty square
+///
+/// Returns `NoEmptySquare` if the board is full.
+///
+/// # Safety
+///
+/// `game` must be null or a valid handle.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ffi::*;
+///
+/// let board = [0; G2048_SQUARES];
+/// unsafe {
+///     let game = g2048_from_board(board.as_ptr(), 7);
+///     assert_eq!(g2048_spawn(game), G2048Status::Ok);
+///     g2048_free(game);
+/// }
+/// ```
+///
+#[no_mangle]
+pub unsafe extern "C" fn g2048_spawn(game: *mut G2048Game) -> G2048Status {
+    match game.as_mut() {
+        Some(game) => match game.game.random(&mut game.rng) {
+            Ok(()) => G2048Status::Ok,
+            Err(error) => error.into(),
+        },
+        None => G2048Status::NullPointer,
+    }
+}
+
+/// Copy the exponents of the numbers in array form into a buffer
+///
+/// Returns `InvalidArgument` if the buffer is shorter than [`G2048_SQUARES`].
+///
+/// # Safety
+///
+/// `game` must be null or a valid handle and `buffer` must be null or point
+/// to `length` writable bytes.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ffi::*;
+///
+/// let mut board = [0; G2048_SQUARES];
+/// unsafe {
+///     let game = g2048_new(7);
+///     assert_eq!(g2048_board(game, board.as_mut_ptr(), board.len()), G2048Status::Ok);
+///     assert_eq!(g2048_board(game, board.as_mut_ptr(), 4), G2048Status::InvalidArgument);
+///     g2048_free(game);
+/// }
+/// ```
+///
+#[no_mangle]
+pub unsafe extern "C" fn g2048_board(
+    game: *const G2048Game,
+    buffer: *mut u8,
+    length: usize,
+) -> G2048Status {
+    let game = match game.as_ref() {
+        Some(game) if !buffer.is_null() => game,
+        _ => return G2048Status::NullPointer,
+    };
+    if length < G2048_SQUARES {
+        return G2048Status::InvalidArgument;
+    }
+    std::slice::from_raw_parts_mut(buffer, G2048_SQUARES).copy_from_slice(&game.game.as_array());
+    G2048Status::Ok
+}
+
+/// The score gained by merges, 0 for a null handle
+///
+/// # Safety
+///
+/// `game` must be null or a valid handle.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ffi::*;
+///
+/// unsafe {
+///     let game = g2048_new(7);
+///     assert_eq!(g2048_score(game), 0);
+///     g2048_free(game);
+/// }
+/// ```
+///
+#[no_mangle]
+pub unsafe extern "C" fn g2048_score(game: *const G2048Game) -> u64 {
+    game.as_ref().map_or(0, |game| game.score)
+}
+
+/// Whether no direction changes the board, true for a null handle
+///
+/// # Safety
+///
+/// `game` must be null or a valid handle.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ffi::*;
+///
+/// unsafe {
+///     let game = g2048_new(7);
+///     assert!(!g2048_is_over(game));
+///     g2048_free(game);
+/// }
+/// ```
+///
+#[no_mangle]
+pub unsafe extern "C" fn g2048_is_over(game: *const G2048Game) -> bool {
+    game.as_ref().is_none_or(|game| {
+        let board = game.game.as_array();
+        DIRECTIONS
+            .iter()
+            .all(|direction| ArrayModel::from(board).slide(*direction).is_none())
+    })
+}
+
+/// A static, null terminated description of a status code
+///
+/// Takes the code as an integer since C may pass any value, codes that are not
+/// a [`G2048Status`] are described as `Unknown status`.
+///
+/// # Examples
+///
+/// ```
+/// use std::ffi::CStr;
+/// use game_2048_model::ffi::*;
+///
+/// let message = unsafe { CStr::from_ptr(g2048_status_message(G2048Status::NoEmptySquare as i32)) };
+/// assert_eq!(message.to_str().unwrap(), "No empty spaces for new numbers");
+/// ```
+///
+#[no_mangle]
+pub extern "C" fn g2048_status_message(status: i32) -> *const c_char {
+    let statuses = [
+        G2048Status::Ok,
+        G2048Status::Unchanged,
+        G2048Status::NoEmptySquare,
+        G2048Status::NullPointer,
+        G2048Status::InvalidArgument,
+    ];
+    let message: &'static [u8] = match statuses.iter().find(|known| **known as i32 == status) {
+        Some(G2048Status::Ok) => b"Ok\0",
+        Some(G2048Status::Unchanged) => b"The slide does not change the board\0",
+        Some(G2048Status::NoEmptySquare) => b"No empty spaces for new numbers\0",
+        Some(G2048Status::NullPointer) => b"A pointer argument is null\0",
+        Some(G2048Status::InvalidArgument) => b"An argument is out of range\0",
+        None => b"Unknown status\0",
+    };
+    message.as_ptr() as *const c_char
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Model;
+
+    fn board(game: *const G2048Game) -> ArrayBoard {
+        let mut board = [0; G2048_SQUARES];
+        assert_eq!(
+            unsafe { g2048_board(game, board.as_mut_ptr(), board.len()) },
+            G2048Status::Ok
+        );
+        board
+    }
+
+    mod g2048_squares {
+        use super::*;
+
+        #[test]
+        fn matches_the_board_size() {
+            assert_eq!(G2048_SQUARES, BOARD_SIZE * BOARD_SIZE);
+        }
+    }
+
+    mod g2048_new {
+        use super::*;
+
+        #[test]
+        fn same_seed_same_game() {
+            let first = g2048_new(3);
+            let second = g2048_new(3);
+
+            assert_eq!(board(first), board(second));
+            unsafe {
+                g2048_free(first);
+                g2048_free(second);
+            }
+        }
+    }
+
+    mod g2048_from_board {
+        use super::*;
+
+        #[test]
+        fn rejects_invalid_boards() {
+            let mut invalid = [0; G2048_SQUARES];
+            invalid[5] = MAX_EXPONENT + 1;
+
+            unsafe {
+                assert!(g2048_from_board(ptr::null(), 0).is_null());
+                assert!(g2048_from_board(invalid.as_ptr(), 0).is_null());
+            }
+        }
+    }
+
+    mod g2048_slide {
+        use super::*;
+
+        #[test]
+        fn matches_the_engine() {
+            let game = g2048_new(5);
+            let mut turn = 0;
+            unsafe {
+                while !g2048_is_over(game) {
+                    let mut expected = ArrayModel::from(board(game));
+                    let moved = expected.slide(DIRECTIONS[turn % 4]).is_some();
+
+                    let status = g2048_slide(game, (turn % 4) as u32);
+                    assert_eq!(status == G2048Status::Ok, moved);
+                    assert_eq!(board(game), expected.as_array());
+                    if moved {
+                        assert_eq!(g2048_spawn(game), G2048Status::Ok);
+                    }
+                    turn += 1;
+                }
+                assert!(g2048_score(game) > 0);
+                g2048_free(game);
+            }
+        }
+    }
+
+    mod g2048_spawn {
+        use super::*;
+
+        #[test]
+        fn full_board() {
+            let full = [1, 2, 1, 2, 2, 1, 2, 1, 1, 2, 1, 2, 2, 1, 2, 1];
+            unsafe {
+                let game = g2048_from_board(full.as_ptr(), 0);
+
+                assert_eq!(g2048_spawn(game), G2048Status::NoEmptySquare);
+                assert!(g2048_is_over(game));
+                g2048_free(game);
+            }
+        }
+    }
+
+    mod g2048_status_message {
+        use super::*;
+        use std::ffi::CStr;
+
+        fn message(status: i32) -> &'static str {
+            unsafe { CStr::from_ptr(g2048_status_message(status)) }
+                .to_str()
+                .unwrap()
+        }
+
+        #[test]
+        fn describes_every_status() {
+            assert_eq!(message(G2048Status::Ok as i32), "Ok");
+            assert_eq!(
+                message(G2048Status::InvalidArgument as i32),
+                "An argument is out of range"
+            );
+        }
+
+        #[test]
+        fn unknown_codes() {
+            for status in [-1, 5, i32::MAX, i32::MIN].iter() {
+                assert_eq!(message(*status), "Unknown status");
+            }
+        }
+    }
+
+    mod header {
+        #[test]
+        fn committed_header_is_current() {
+            let generated = include_str!(concat!(env!("OUT_DIR"), "/game_2048_model.h"));
+            let committed = include_str!("../../include/game_2048_model.h");
+
+            assert!(
+                generated == committed,
+                "include/game_2048_model.h is stale, replace it with {}",
+                concat!(env!("OUT_DIR"), "/game_2048_model.h")
+            );
+        }
+    }
+
+    mod null_handles {
+        use super::*;
+
+        #[test]
+        fn report_null_pointer() {
+            let mut buffer = [0; G2048_SQUARES];
+            unsafe {
+                assert_eq!(
+                    g2048_slide(ptr::null_mut(), G2048_UP),
+                    G2048Status::NullPointer
+                );
+                assert_eq!(g2048_spawn(ptr::null_mut()), G2048Status::NullPointer);
+                assert_eq!(
+                    g2048_board(ptr::null(), buffer.as_mut_ptr(), buffer.len()),
+                    G2048Status::NullPointer
+                );
+                assert_eq!(g2048_score(ptr::null()), 0);
+                assert!(g2048_is_over(ptr::null()));
+            }
+        }
+    }
+}
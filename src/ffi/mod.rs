@@ -0,0 +1,23 @@
+//! C bindings, enabled by the `ffi` feature
+//!
+//! The functions here make the engine usable from any language with a C
+//! foreign function interface, like C++ or C# in Unity. Games are opaque
+//! [`G2048Game`] handles, calls report errors with a [`G2048Status`] instead of
+//! panicking. `cargo build --release` builds the shared library
+//! `libgame_2048_model_ffi` from the wrapper crate in `ffi/`. The C header is
+//! `include/game_2048_model.h`, the build script generates it in `OUT_DIR`
+//! and the tests fail until the committed copy matches.
+//!
+//! ```c
+//! G2048Game *game = g2048_new(42);
+//! if (g2048_slide(game, G2048_LEFT) == G2048_STATUS_OK) {
+//!     g2048_spawn(game);
+//! }
+//! uint8_t board[G2048_SQUARES];
+//! g2048_board(game, board, G2048_SQUARES);
+//! g2048_free(game);
+//! ```
+
+mod game;
+
+pub use game::*;
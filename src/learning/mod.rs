@@ -0,0 +1,24 @@
+//! Learned value functions and self-play training
+//!
+//! ```
+//! use game_2048_model::learning::NTupleNetwork;
+//! use game_2048_model::models::{ArrayModel, Model};
+//! use rand::rngs::StdRng;
+//! use rand::SeedableRng;
+//!
+//! let mut network = NTupleNetwork::rows_and_squares();
+//! let mut rng = StdRng::seed_from_u64(42);
+//! network.train_episode::<ArrayModel, _>(&mut rng);
+//!
+//! let game = ArrayModel::from([
+//!     [0, 0, 0, 0],
+//!     [4, 0, 0, 2],
+//!     [2, 8, 4, 0],
+//!     [3, 8, 6, 4]
+//! ]);
+//! assert!(network.best_direction(&game).is_some());
+//! ```
+
+mod ntuple;
+
+pub use ntuple::{Episode, LearningRate, NTupleNetwork};
@@ -0,0 +1,732 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+use rand::prelude::*;
+
+use crate::ai::Agent;
+use crate::base::*;
+
+/// Number of distinct exponents a single square can take in a tuple, larger exponents are capped
+const EXPONENTS: usize = 16;
+
+const MAGIC: &[u8; 4] = b"NTUP";
+const VERSION: u32 = 1;
+/// Largest number of tuples of an [`NTupleNetwork`]
+const MAX_TUPLES: u32 = 64;
+/// Largest number of weights of an [`NTupleNetwork`], 1 GiB of weights
+const MAX_WEIGHTS: usize = 1 << 28;
+
+/// The number of weights of a tuple of `length` squares, `None` if it is more than [`MAX_WEIGHTS`]
+fn table_size(length: usize) -> Option<usize> {
+    u32::try_from(length)
+        .ok()
+        .and_then(|length| EXPONENTS.checked_pow(length))
+        .filter(|size| *size <= MAX_WEIGHTS)
+}
+
+/// How the weights of an [`NTupleNetwork`] are adjusted while learning
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LearningRate {
+    /// Every weight is moved by the same rate
+    Constant(f32),
+    /// Temporal coherence learning, every weight gets its own rate scaled by the given rate
+    ///
+    /// The rate of a weight is the ratio between the sum of its errors and the sum
+    /// of its absolute errors, so weights that keep being corrected in the same
+    /// direction keep learning while weights that oscillate slow down.
+    TemporalCoherence(f32),
+}
+
+/// Result of one self-play game played while training
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Episode {
+    /// The score gained by merges
+    pub score: u64,
+    /// The number of slides played
+    pub moves: usize,
+    /// The largest exponent on the final board
+    pub max_exponent: BoardElement,
+}
+
+#[derive(Debug, Clone)]
+struct Coherence {
+    errors: Vec<Vec<f32>>,
+    absolute_errors: Vec<Vec<f32>>,
+}
+
+/// A value function over boards made from n-tuples of squares
+///
+/// Each tuple is a list of squares on the board (indexed like an
+/// [`ArrayBoard`]) and has a weight for every combination of exponents on
+/// those squares. The value of a board is the sum of the weights selected by
+/// every tuple on each of the eight rotations and reflections of the board, so
+/// symmetric positions share their weights.
+///
+/// The network is trained with TD(0) on afterstates, the boards directly after
+/// a slide and before a number is spawned, as described by Szubert and
+/// Jaśkowski in *Temporal Difference Learning of N-Tuple Networks for the Game
+/// 2048*.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::learning::NTupleNetwork;
+/// use game_2048_model::models::ArrayModel;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut network = NTupleNetwork::rows_and_squares();
+/// let mut rng = StdRng::seed_from_u64(42);
+/// for _ in 0..10 {
+///     let episode = network.train_episode::<ArrayModel, _>(&mut rng);
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct NTupleNetwork {
+    tuples: Vec<Vec<usize>>,
+    symmetries: Vec<Vec<Vec<usize>>>,
+    weights: Vec<Vec<f32>>,
+    learning_rate: LearningRate,
+    coherence: Option<Coherence>,
+}
+
+impl NTupleNetwork {
+    /// Create a network with all weights set to zero
+    ///
+    /// The network learns with a constant rate of 0.1 by default, divided by
+    /// the number of weights that make up the value of a board.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a tuple is empty or contains a square outside the board, if
+    /// there are more than 64 tuples or if the tuples have more than 2^28
+    /// weights together, which limits a tuple to 7 squares. Every network
+    /// that can be created can be saved and loaded again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::learning::NTupleNetwork;
+    ///
+    /// let network = NTupleNetwork::new(&[&[0, 1, 2, 3], &[0, 1, 4, 5]]);
+    ///
+    /// assert_eq!(network.value(&[0; 16]), 0.0);
+    /// ```
+    ///
+    pub fn new(tuples: &[&[usize]]) -> Self {
+        assert!(
+            tuples.len() <= MAX_TUPLES as usize,
+            "A network can have at most 64 tuples"
+        );
+        for tuple in tuples.iter() {
+            assert!(!tuple.is_empty(), "A tuple must have at least 1 square");
+            assert!(
+                tuple.iter().all(|square| *square < BOARD_SIZE * BOARD_SIZE),
+                "A tuple can only contain squares on the board"
+            );
+        }
+        let total = tuples.iter().try_fold(0usize, |total, tuple| {
+            table_size(tuple.len())
+                .and_then(|size| total.checked_add(size))
+                .filter(|total| *total <= MAX_WEIGHTS)
+        });
+        assert!(total.is_some(), "A network can have at most 2^28 weights");
+
+        let tuples: Vec<Vec<usize>> = tuples.iter().map(|tuple| tuple.to_vec()).collect();
+        let symmetries = tuples
+            .iter()
+            .map(|tuple| {
+                (0..8)
+                    .map(|symmetry| {
+                        tuple
+                            .iter()
+                            .map(|square| symmetric_square(*square, symmetry))
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        let weights = tuples
+            .iter()
+            .map(|tuple| vec![0.0; EXPONENTS.pow(tuple.len() as u32)])
+            .collect();
+
+        NTupleNetwork {
+            tuples,
+            symmetries,
+            weights,
+            learning_rate: LearningRate::Constant(0.1),
+            coherence: None,
+        }
+    }
+
+    /// A small network of two straight lines and two squares of four
+    ///
+    /// Uses about a megabyte of weights and learns quickly enough to start
+    /// reaching 2048 within a few thousand training games.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::learning::NTupleNetwork;
+    ///
+    /// let network = NTupleNetwork::rows_and_squares();
+    /// ```
+    ///
+    pub fn rows_and_squares() -> Self {
+        NTupleNetwork::new(&[&[0, 1, 2, 3], &[4, 5, 6, 7], &[0, 1, 4, 5], &[5, 6, 9, 10]])
+    }
+
+    /// The network of four 6-tuples commonly used for strong players
+    ///
+    /// Uses about 270 megabytes of weights.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use game_2048_model::learning::NTupleNetwork;
+    ///
+    /// let network = NTupleNetwork::six_tuples();
+    /// ```
+    ///
+    pub fn six_tuples() -> Self {
+        NTupleNetwork::new(&[
+            &[0, 1, 2, 3, 4, 5],
+            &[4, 5, 6, 7, 8, 9],
+            &[0, 1, 2, 4, 5, 6],
+            &[4, 5, 6, 8, 9, 10],
+        ])
+    }
+
+    /// Set how the weights are adjusted while learning
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::learning::{LearningRate, NTupleNetwork};
+    ///
+    /// let network = NTupleNetwork::rows_and_squares()
+    ///     .learning_rate(LearningRate::TemporalCoherence(1.0));
+    /// ```
+    ///
+    pub fn learning_rate(mut self, learning_rate: LearningRate) -> Self {
+        self.learning_rate = learning_rate;
+        self.coherence = match learning_rate {
+            LearningRate::Constant(_) => None,
+            LearningRate::TemporalCoherence(_) => {
+                let zeros: Vec<Vec<f32>> =
+                    self.weights.iter().map(|w| vec![0.0; w.len()]).collect();
+                Some(Coherence {
+                    errors: zeros.clone(),
+                    absolute_errors: zeros,
+                })
+            }
+        };
+        self
+    }
+
+    /// The weight indices selected by every tuple and symmetry on the board
+    fn features<'a>(&'a self, board: &'a ArrayBoard) -> impl Iterator<Item = (usize, usize)> + 'a {
+        self.symmetries
+            .iter()
+            .enumerate()
+            .flat_map(move |(tuple, symmetries)| {
+                symmetries.iter().map(move |squares| {
+                    let index = squares.iter().fold(0, |index, square| {
+                        index * EXPONENTS + usize::from(board[*square]).min(EXPONENTS - 1)
+                    });
+                    (tuple, index)
+                })
+            })
+    }
+
+    /// The estimated score still to be gained from the board
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::learning::NTupleNetwork;
+    ///
+    /// let network = NTupleNetwork::rows_and_squares();
+    ///
+    /// assert_eq!(network.value(&[0; 16]), 0.0);
+    /// ```
+    ///
+    pub fn value(&self, board: &ArrayBoard) -> f64 {
+        self.features(board)
+            .map(|(tuple, index)| f64::from(self.weights[tuple][index]))
+            .sum()
+    }
+
+    /// Move the value of the board towards the target
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::learning::NTupleNetwork;
+    ///
+    /// let mut network = NTupleNetwork::rows_and_squares();
+    /// let board = [1,2,0,0,0,0,0,0,0,0,0,0,0,0,0,0];
+    /// network.learn(&board, 100.0);
+    ///
+    /// assert!(network.value(&board) > 0.0);
+    /// ```
+    ///
+    pub fn learn(&mut self, board: &ArrayBoard, target: f64) {
+        let error = (target - self.value(board)) as f32;
+        let features: Vec<(usize, usize)> = self.features(board).collect();
+        let count = features.len() as f32;
+        match (self.learning_rate, &mut self.coherence) {
+            (LearningRate::Constant(rate), _) => {
+                for (tuple, index) in features {
+                    self.weights[tuple][index] += rate / count * error;
+                }
+            }
+            (LearningRate::TemporalCoherence(rate), Some(coherence)) => {
+                for (tuple, index) in features {
+                    let absolute = coherence.absolute_errors[tuple][index];
+                    let coherence_rate = if absolute == 0.0 {
+                        1.0
+                    } else {
+                        coherence.errors[tuple][index].abs() / absolute
+                    };
+                    self.weights[tuple][index] += rate / count * coherence_rate * error;
+                    coherence.errors[tuple][index] += error;
+                    coherence.absolute_errors[tuple][index] += error.abs();
+                }
+            }
+            (LearningRate::TemporalCoherence(_), None) => {
+                unreachable!("Temporal coherence is always set up with its learning rate")
+            }
+        }
+    }
+
+    /// The direction maximising the score of the slide plus the value of the afterstate
+    ///
+    /// Returns the direction together with the afterstate and the score of the slide.
+    fn choose_afterstate<M: Model>(
+        &self,
+        board: ArrayBoard,
+    ) -> Option<(Directions, ArrayBoard, u64)> {
+        let mut best: Option<(Directions, ArrayBoard, u64, f64)> = None;
+        for direction in DIRECTIONS.iter() {
+            let mut game = M::from(board);
+            if game.slide(*direction).is_none() {
+                continue;
+            }
+            let after = game.as_array();
+            let reward = slide_score(&board, &after);
+            let value = reward as f64 + self.value(&after);
+            if best.is_none_or(|(_, _, _, best_value)| value > best_value) {
+                best = Some((*direction, after, reward, value));
+            }
+        }
+        best.map(|(direction, after, reward, _)| (direction, after, reward))
+    }
+
+    /// Returns the direction the network considers best, or `None` if no direction changes the board
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::learning::NTupleNetwork;
+    /// use game_2048_model::models::{Directions, Matrix};
+    ///
+    /// let network = NTupleNetwork::rows_and_squares();
+    /// let game = Matrix::from([
+    ///     1,1,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,0
+    /// ]);
+    ///
+    /// // An untrained network only looks at the score of the slide.
+    /// assert_eq!(network.best_direction(&game), Some(Directions::Right));
+    /// ```
+    ///
+    pub fn best_direction<M: Model>(&self, game: &M) -> Option<Directions> {
+        self.choose_afterstate::<M>(game.as_array())
+            .map(|(direction, _, _)| direction)
+    }
+
+    /// Play one game against itself, learning from every afterstate
+    ///
+    /// The game starts with two spawned numbers, like a new game.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::learning::NTupleNetwork;
+    /// use game_2048_model::models::Matrix;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut network = NTupleNetwork::rows_and_squares();
+    /// let episode = network.train_episode::<Matrix, _>(&mut StdRng::seed_from_u64(1));
+    ///
+    /// assert!(episode.moves > 0);
+    /// ```
+    ///
+    pub fn train_episode<M: Model, R: Rng>(&mut self, rng: &mut R) -> Episode {
+        let mut game = M::new();
+        let _ = game.random(rng);
+        let _ = game.random(rng);
+
+        let mut episode = Episode {
+            score: 0,
+            moves: 0,
+            max_exponent: 0,
+        };
+        let mut previous: Option<ArrayBoard> = None;
+        loop {
+            let board = game.as_array();
+            match self.choose_afterstate::<M>(board) {
+                Some((_, after, reward)) => {
+                    if let Some(previous) = previous {
+                        let target = reward as f64 + self.value(&after);
+                        self.learn(&previous, target);
+                    }
+                    previous = Some(after);
+                    episode.score += reward;
+                    episode.moves += 1;
+                    game = M::from(after);
+                    let _ = game.random(rng);
+                }
+                None => {
+                    if let Some(previous) = previous {
+                        self.learn(&previous, 0.0);
+                    }
+                    episode.max_exponent = board.iter().max().copied().unwrap_or(0);
+                    return episode;
+                }
+            }
+        }
+    }
+
+    /// Write the tuples and weights of the network
+    ///
+    /// The learning rate is not saved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::learning::NTupleNetwork;
+    ///
+    /// let mut network = NTupleNetwork::new(&[&[0, 1]]);
+    /// network.learn(&[1,2,0,0,0,0,0,0,0,0,0,0,0,0,0,0], 10.0);
+    ///
+    /// let mut buffer = Vec::new();
+    /// network.save(&mut buffer).unwrap();
+    /// let loaded = NTupleNetwork::load(&mut buffer.as_slice()).unwrap();
+    ///
+    /// assert_eq!(loaded.value(&[1,2,0,0,0,0,0,0,0,0,0,0,0,0,0,0]), network.value(&[1,2,0,0,0,0,0,0,0,0,0,0,0,0,0,0]));
+    /// ```
+    ///
+    pub fn save<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.tuples.len() as u32).to_le_bytes())?;
+        for tuple in self.tuples.iter() {
+            writer.write_all(&(tuple.len() as u32).to_le_bytes())?;
+            for square in tuple.iter() {
+                writer.write_all(&(*square as u32).to_le_bytes())?;
+            }
+        }
+        for weights in self.weights.iter() {
+            for weight in weights.iter() {
+                writer.write_all(&weight.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a network written by [`NTupleNetwork::save`]
+    ///
+    /// The loaded network learns with the default constant rate. Networks of
+    /// more than 64 tuples or 2^28 weights are rejected before anything is
+    /// allocated for them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::learning::NTupleNetwork;
+    ///
+    /// assert!(NTupleNetwork::load(&mut "not a network".as_bytes()).is_err());
+    /// ```
+    ///
+    pub fn load<R: Read>(reader: &mut R) -> io::Result<Self> {
+        fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+            let mut bytes = [0; 4];
+            reader.read_exact(&mut bytes)?;
+            Ok(u32::from_le_bytes(bytes))
+        }
+        fn invalid(message: &str) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        }
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("Not an n-tuple network"));
+        }
+        if read_u32(reader)? != VERSION {
+            return Err(invalid("Unsupported n-tuple network version"));
+        }
+
+        let count = read_u32(reader)?;
+        if count > MAX_TUPLES {
+            return Err(invalid("Too many tuples"));
+        }
+        let mut tuples = Vec::new();
+        let mut total = 0;
+        for _ in 0..count {
+            let length = read_u32(reader)?;
+            if length == 0 {
+                return Err(invalid("Invalid tuple length"));
+            }
+            total += table_size(length as usize).ok_or_else(|| invalid("Too many weights"))?;
+            if total > MAX_WEIGHTS {
+                return Err(invalid("Too many weights"));
+            }
+            let mut tuple = Vec::new();
+            for _ in 0..length {
+                let square = read_u32(reader)? as usize;
+                if square >= BOARD_SIZE * BOARD_SIZE {
+                    return Err(invalid("Tuple square outside the board"));
+                }
+                tuple.push(square);
+            }
+            tuples.push(tuple);
+        }
+
+        let slices: Vec<&[usize]> = tuples.iter().map(|tuple| tuple.as_slice()).collect();
+        let mut network = NTupleNetwork::new(&slices);
+        for weights in network.weights.iter_mut() {
+            for weight in weights.iter_mut() {
+                let mut bytes = [0; 4];
+                reader.read_exact(&mut bytes)?;
+                *weight = f32::from_le_bytes(bytes);
+            }
+        }
+        Ok(network)
+    }
+}
+
+impl<M: Model> Agent<M> for NTupleNetwork {
+    fn choose(&mut self, game: &M) -> Option<Directions> {
+        self.best_direction(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LearningRate, NTupleNetwork};
+    use crate::models::{ArrayModel, Directions, Matrix};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    mod value {
+        use super::NTupleNetwork;
+
+        #[rustfmt::skip]
+        #[test]
+        fn symmetric_boards_have_the_same_value() {
+            let mut network = NTupleNetwork::rows_and_squares();
+            let board = [
+                1,2,3,0,
+                0,4,0,0,
+                0,0,0,0,
+                5,0,0,0
+            ];
+            let rotated = [
+                5,0,0,1,
+                0,0,4,2,
+                0,0,0,3,
+                0,0,0,0
+            ];
+            network.learn(&board, 50.0);
+
+            assert!(network.value(&board) > 0.0);
+            assert!((network.value(&board) - network.value(&rotated)).abs() < 1e-4);
+        }
+    }
+
+    mod learn {
+        use super::{LearningRate, NTupleNetwork};
+
+        fn third_step(learning_rate: LearningRate) -> f64 {
+            let mut network = NTupleNetwork::new(&[&[0, 1]]).learning_rate(learning_rate);
+            let board = [1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+            network.learn(&board, 8.0);
+            network.learn(&board, 0.0);
+            let before = network.value(&board);
+            network.learn(&board, 8.0);
+            (network.value(&board) - before) / (8.0 - before)
+        }
+
+        #[test]
+        fn moves_value_towards_target() {
+            let mut network = NTupleNetwork::new(&[&[0, 1]]);
+            let board = [1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+            network.learn(&board, 8.0);
+
+            assert!(network.value(&board) > 0.0);
+            assert!(network.value(&board) < 8.0);
+        }
+
+        #[test]
+        fn temporal_coherence_slows_down_oscillating_weights() {
+            let constant = third_step(LearningRate::Constant(0.1));
+            let coherence = third_step(LearningRate::TemporalCoherence(0.1));
+
+            assert!(coherence > 0.0);
+            assert!(coherence < constant);
+        }
+    }
+
+    mod best_direction {
+        use super::{ArrayModel, Directions, NTupleNetwork};
+
+        #[rustfmt::skip]
+        #[test]
+        fn returns_none_without_legal_moves() {
+            let game = ArrayModel::from([
+                1,2,1,2,
+                2,1,2,1,
+                1,2,1,2,
+                2,1,2,1
+            ]);
+
+            assert_eq!(NTupleNetwork::rows_and_squares().best_direction(&game), None);
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn returns_the_only_legal_move() {
+            let game = ArrayModel::from([
+                0,2,1,2,
+                0,1,2,1,
+                0,2,1,2,
+                0,1,2,1
+            ]);
+
+            assert_eq!(NTupleNetwork::rows_and_squares().best_direction(&game), Some(Directions::Left));
+        }
+    }
+
+    mod train_episode {
+        use super::{ArrayModel, Matrix, NTupleNetwork, SeedableRng, StdRng};
+
+        #[test]
+        fn learns_from_the_game() {
+            let mut network = NTupleNetwork::rows_and_squares();
+            let episode = network.train_episode::<ArrayModel, _>(&mut StdRng::seed_from_u64(3));
+
+            assert!(episode.moves > 0);
+            assert!(episode.max_exponent > 1);
+            assert!(network
+                .weights
+                .iter()
+                .flatten()
+                .any(|weight| *weight != 0.0));
+        }
+
+        #[test]
+        fn same_seed_same_episode() {
+            let mut first = NTupleNetwork::rows_and_squares();
+            let mut second = NTupleNetwork::rows_and_squares();
+
+            assert_eq!(
+                first.train_episode::<Matrix, _>(&mut StdRng::seed_from_u64(3)),
+                second.train_episode::<Matrix, _>(&mut StdRng::seed_from_u64(3))
+            );
+        }
+    }
+
+    mod new {
+        use super::NTupleNetwork;
+
+        #[test]
+        #[should_panic(expected = "at most 2^28 weights")]
+        fn rejects_tables_too_large_to_load() {
+            NTupleNetwork::new(&[&[0, 1, 2, 3, 4, 5, 6], &[8, 9, 10, 11, 12, 13, 14]]);
+        }
+
+        #[test]
+        #[should_panic(expected = "at most 2^28 weights")]
+        fn rejects_tuples_of_eight_squares() {
+            NTupleNetwork::new(&[&[0, 1, 2, 3, 4, 5, 6, 7]]);
+        }
+
+        #[test]
+        #[should_panic(expected = "at most 64 tuples")]
+        fn rejects_too_many_tuples() {
+            let tuple: &[usize] = &[0];
+            NTupleNetwork::new(&[tuple; 65]);
+        }
+    }
+
+    mod load {
+        use super::NTupleNetwork;
+
+        fn header(lengths: &[u32]) -> Vec<u8> {
+            let mut bytes = b"NTUP".to_vec();
+            bytes.extend_from_slice(&1u32.to_le_bytes());
+            bytes.extend_from_slice(&(lengths.len() as u32).to_le_bytes());
+            for length in lengths.iter() {
+                bytes.extend_from_slice(&length.to_le_bytes());
+                for square in 0..*length {
+                    bytes.extend_from_slice(&square.to_le_bytes());
+                }
+            }
+            bytes
+        }
+
+        #[test]
+        fn rejects_too_many_tuples() {
+            let mut bytes = b"NTUP".to_vec();
+            bytes.extend_from_slice(&1u32.to_le_bytes());
+            bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+
+            assert!(NTupleNetwork::load(&mut bytes.as_slice()).is_err());
+        }
+
+        #[test]
+        fn rejects_tables_too_large_to_allocate() {
+            assert!(NTupleNetwork::load(&mut header(&[8]).as_slice()).is_err());
+            assert!(NTupleNetwork::load(&mut header(&[7; 20]).as_slice()).is_err());
+        }
+    }
+
+    mod save {
+        use super::NTupleNetwork;
+
+        #[test]
+        fn round_trips_tuples_and_weights() {
+            let mut network = NTupleNetwork::new(&[&[0, 1, 2], &[5, 9]]);
+            let board = [1, 2, 3, 0, 0, 4, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0];
+            network.learn(&board, 10.0);
+
+            let mut buffer = Vec::new();
+            network.save(&mut buffer).unwrap();
+            let loaded = NTupleNetwork::load(&mut buffer.as_slice()).unwrap();
+
+            assert_eq!(loaded.tuples, network.tuples);
+            assert_eq!(loaded.weights, network.weights);
+        }
+
+        #[test]
+        fn rejects_truncated_data() {
+            let network = NTupleNetwork::new(&[&[0, 1]]);
+            let mut buffer = Vec::new();
+            network.save(&mut buffer).unwrap();
+            buffer.truncate(buffer.len() - 1);
+
+            assert!(NTupleNetwork::load(&mut buffer.as_slice()).is_err());
+        }
+    }
+}
@@ -40,9 +40,34 @@
 //! game.slide(Directions::Down);
 //! ```
 
-pub mod models;
+pub mod ai;
 mod base;
+pub mod encoding;
+pub mod environment;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod learning;
+// The engine code predates these lints
+#[allow(
+    clippy::bool_assert_comparison,
+    clippy::clone_on_copy,
+    clippy::nonminimal_bool,
+    clippy::partialeq_to_none,
+    clippy::redundant_field_names,
+    clippy::unnecessary_cast
+)]
+pub mod models;
+pub mod replay;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod solver;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use base::*;
 
+// Also exports the base items already exported by models
+#[allow(unused_imports)]
 pub mod prelude;
@@ -0,0 +1,61 @@
+use std::error;
+use std::fmt;
+
+use crate::base::*;
+
+/// Why a replay does not re-simulate to the recorded game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The move at this index does not change the board
+    IllegalMove { turn: usize, direction: Directions },
+    /// The spawn at this index is on a square that is not empty
+    OccupiedSquare { spawn: usize, square: usize },
+    /// The spawn at this index is outside the board or not a number that can spawn
+    InvalidSpawn { spawn: usize },
+    /// The number of recorded spawns does not match the number of moves
+    SpawnCount { expected: usize, found: usize },
+    /// The score of the replay is not the score of the moves
+    ScoreMismatch { claimed: u64, actual: u64 },
+    /// The board the replay starts from is not allowed
+    InvalidStart,
+    /// The game does not end with every move blocked
+    NotOver,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::IllegalMove { turn, direction } => {
+                write!(
+                    f,
+                    "Move {} ({:?}) does not change the board",
+                    turn, direction
+                )
+            }
+            ReplayError::OccupiedSquare { spawn, square } => {
+                write!(f, "Spawn {} is on the occupied square {}", spawn, square)
+            }
+            ReplayError::InvalidSpawn { spawn } => {
+                write!(f, "Spawn {} is not a valid spawn", spawn)
+            }
+            ReplayError::SpawnCount { expected, found } => {
+                write!(f, "Expected {} spawns but found {}", expected, found)
+            }
+            ReplayError::ScoreMismatch { claimed, actual } => {
+                write!(
+                    f,
+                    "Claimed a score of {} but the moves score {}",
+                    claimed, actual
+                )
+            }
+            ReplayError::InvalidStart => write!(f, "The starting board is not allowed"),
+            ReplayError::NotOver => write!(f, "The game is not over"),
+        }
+    }
+}
+
+impl error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
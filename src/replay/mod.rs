@@ -0,0 +1,37 @@
+//! Recorded games that can be stored and played back
+//!
+//! A [`Replay`] holds everything needed to play a game again: the board it
+//! starts from, the spawns either as a seed or one by one, the moves and the
+//! final score. Replays are stored in a small binary format with a versioned
+//! header and are validated by playing them again on any game model.
+//!
+//! Games with explicit spawns can also be written as text in move notation,
+//! one [`Turn`] per line, for pasting into bug reports and diffing.
+//! Submitted games can be checked by a [`Verifier`] before they are trusted.
+//!
+//! ```
+//! use game_2048_model::ai::PreferenceOrder;
+//! use game_2048_model::models::ArrayModel;
+//! use game_2048_model::replay::Replay;
+//!
+//! let replay = Replay::record::<ArrayModel, _>(&mut PreferenceOrder::corner(), 42);
+//!
+//! let mut file = Vec::new();
+//! replay.write(&mut file).unwrap();
+//! let loaded = Replay::read(&mut file.as_slice()).unwrap();
+//!
+//! assert_eq!(loaded, replay);
+//! assert!(loaded.simulate::<ArrayModel>().is_ok());
+//! ```
+
+mod error;
+mod notation;
+mod recorder;
+mod replay_file;
+mod verifier;
+
+pub use error::ReplayError;
+pub use notation::{format_turns, parse_turns, NotationError, Turn};
+pub use recorder::Recorder;
+pub use replay_file::{Replay, Spawn, Spawns};
+pub use verifier::{Verified, Verifier};
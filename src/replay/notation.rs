@@ -0,0 +1,408 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Replay, Spawn, Spawns};
+use crate::base::*;
+use crate::models::ArrayModel;
+
+/// One line of a game in move notation
+///
+/// A turn is written as the direction letter `U`, `R`, `D` or `L` followed by
+/// the spawn after the move, like `L b3:2`. The spawn is the column letter
+/// `a`-`d` from the left, the row number `1`-`4` from the top and the number
+/// that spawned. The two spawns starting a game have no direction.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::models::Directions;
+/// use game_2048_model::replay::{Spawn, Turn};
+///
+/// let turn: Turn = "L b3:2".parse().unwrap();
+///
+/// assert_eq!(turn.direction, Some(Directions::Left));
+/// assert_eq!(turn.spawn, Spawn { square: 9, exponent: 1 });
+/// assert_eq!(turn.to_string(), "L b3:2");
+/// ```
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Turn {
+    /// The move of the turn, `None` for the spawns starting a game
+    pub direction: Option<Directions>,
+    /// The number spawned after the move
+    pub spawn: Spawn,
+}
+
+/// A line that is not valid move notation
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::replay::parse_turns;
+///
+/// let error = parse_turns("a1:2\nb1:2\nX c3:4").unwrap_err();
+/// assert_eq!(error.line, 3);
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotationError {
+    /// The line number of the invalid line, starting at 1
+    pub line: usize,
+    /// The invalid line
+    pub text: String,
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {} is not a valid turn: {}", self.line, self.text)
+    }
+}
+
+impl error::Error for NotationError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+impl fmt::Display for Turn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(direction) = self.direction {
+            let letter = match direction {
+                Directions::Up => 'U',
+                Directions::Right => 'R',
+                Directions::Down => 'D',
+                Directions::Left => 'L',
+            };
+            write!(f, "{} ", letter)?;
+        }
+        write!(
+            f,
+            "{}{}:",
+            (b'a' + (self.spawn.square % BOARD_SIZE) as u8) as char,
+            self.spawn.square / BOARD_SIZE + 1,
+        )?;
+        match 1u64.checked_shl(u32::from(self.spawn.exponent)) {
+            Some(value) => write!(f, "{}", value),
+            None => write!(f, "{}", power_of_two(self.spawn.exponent)),
+        }
+    }
+}
+
+/// The decimal digits of a power of two too large for a `u64`
+fn power_of_two(exponent: BoardElement) -> String {
+    // Least significant digit first
+    let mut digits = vec![1u8];
+    for _ in 0..exponent {
+        let mut carry = 0;
+        for digit in digits.iter_mut() {
+            let doubled = *digit * 2 + carry;
+            *digit = doubled % 10;
+            carry = doubled / 10;
+        }
+        if carry > 0 {
+            digits.push(carry);
+        }
+    }
+    digits
+        .iter()
+        .rev()
+        .map(|digit| char::from(b'0' + digit))
+        .collect()
+}
+
+impl FromStr for Turn {
+    type Err = NotationError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = || NotationError {
+            line: 1,
+            text: text.to_string(),
+        };
+
+        let mut parts = text.split_whitespace();
+        let (direction, spawn) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(spawn), None, None) => (None, spawn),
+            (Some(direction), Some(spawn), None) => {
+                let direction = match direction {
+                    "U" => Directions::Up,
+                    "R" => Directions::Right,
+                    "D" => Directions::Down,
+                    "L" => Directions::Left,
+                    _ => return Err(error()),
+                };
+                (Some(direction), spawn)
+            }
+            _ => return Err(error()),
+        };
+
+        let (square, value) = spawn.split_once(':').ok_or_else(error)?;
+        let mut square = square.chars();
+        let (column, row) = match (square.next(), square.next(), square.next()) {
+            (Some(column), Some(row), None) => (column, row),
+            _ => return Err(error()),
+        };
+        let column = (column as usize).wrapping_sub('a' as usize);
+        let row = (row as usize).wrapping_sub('1' as usize);
+        if column >= BOARD_SIZE || row >= BOARD_SIZE {
+            return Err(error());
+        }
+        let value: u64 = value.parse().map_err(|_| error())?;
+        if value < 2 || !value.is_power_of_two() {
+            return Err(error());
+        }
+
+        Ok(Turn {
+            direction,
+            spawn: Spawn {
+                square: row * BOARD_SIZE + column,
+                exponent: value.trailing_zeros() as BoardElement,
+            },
+        })
+    }
+}
+
+/// Read a game written in move notation, one turn per line
+///
+/// Empty lines and lines starting with `#` are skipped, so games can be
+/// annotated when pasted into a bug report.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::replay::parse_turns;
+///
+/// let turns = parse_turns("# The opening spawns\na1:2\nb1:2\n\nL d4:4").unwrap();
+///
+/// assert_eq!(turns.len(), 3);
+/// ```
+///
+pub fn parse_turns(text: &str) -> Result<Vec<Turn>, NotationError> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| {
+            line.parse().map_err(|error: NotationError| NotationError {
+                line: index + 1,
+                ..error
+            })
+        })
+        .collect()
+}
+
+/// Write a game in move notation, one turn per line
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::replay::{format_turns, parse_turns};
+///
+/// let text = "a1:2\nb1:2\nL d4:4\n";
+/// assert_eq!(format_turns(&parse_turns(text).unwrap()), text);
+/// ```
+///
+pub fn format_turns(turns: &[Turn]) -> String {
+    turns.iter().map(|turn| format!("{}\n", turn)).collect()
+}
+
+impl Replay {
+    /// The turns of a replay with explicit spawns starting from an empty board
+    ///
+    /// Returns `None` if the spawns are given by a seed, the board is not empty
+    /// at the start or the spawns do not follow the moves, see
+    /// [`Replay::simulate`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::models::ArrayModel;
+    /// use game_2048_model::replay::{format_turns, Recorder};
+    ///
+    /// let mut recorder = Recorder::new(ArrayModel::from([0; 16]));
+    /// recorder.random(&mut rand::thread_rng()).unwrap();
+    /// recorder.random(&mut rand::thread_rng()).unwrap();
+    ///
+    /// let turns = recorder.finish().turns().unwrap();
+    /// println!("{}", format_turns(&turns));
+    /// ```
+    ///
+    pub fn turns(&self) -> Option<Vec<Turn>> {
+        let spawns = match &self.spawns {
+            Spawns::Explicit(spawns) => spawns,
+            Spawns::Seed(_) => return None,
+        };
+        if self.initial.iter().any(|x| *x != 0) || spawns.len() != 2 + self.moves.len() {
+            return None;
+        }
+
+        let directions = [None, None]
+            .iter()
+            .copied()
+            .chain(self.moves.iter().map(|d| Some(*d)));
+        Some(
+            directions
+                .zip(spawns.iter())
+                .map(|(direction, spawn)| Turn {
+                    direction,
+                    spawn: *spawn,
+                })
+                .collect(),
+        )
+    }
+
+    /// A replay of the turns starting from an empty board
+    ///
+    /// The turns without a direction are the spawns starting the game. The
+    /// score is what the moves score when played, moves that do not change the
+    /// board are kept and reported by [`Replay::simulate`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::models::ArrayModel;
+    /// use game_2048_model::replay::{parse_turns, Replay};
+    ///
+    /// let turns = parse_turns("a1:2\nb1:2\nL d4:4").unwrap();
+    /// let replay = Replay::from_turns(&turns);
+    ///
+    /// assert_eq!(replay.score, 4);
+    /// assert!(replay.simulate::<ArrayModel>().is_ok());
+    /// ```
+    ///
+    pub fn from_turns(turns: &[Turn]) -> Self {
+        let mut board = [0; BOARD_SIZE * BOARD_SIZE];
+        let mut score = 0;
+        for turn in turns.iter() {
+            if let Some(direction) = turn.direction {
+                let mut game = ArrayModel::from(board);
+                if game.slide(direction).is_some() {
+                    score += slide_score(&board, &game.as_array());
+                    board = game.as_array();
+                }
+            }
+            if let Some(square) = board.get_mut(turn.spawn.square) {
+                *square = turn.spawn.exponent;
+            }
+        }
+
+        Replay {
+            initial: [0; BOARD_SIZE * BOARD_SIZE],
+            spawns: Spawns::Explicit(turns.iter().map(|turn| turn.spawn).collect()),
+            moves: turns.iter().filter_map(|turn| turn.direction).collect(),
+            score,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_turns, parse_turns, Turn};
+    use crate::ai::{Agent, PreferenceOrder};
+    use crate::models::{ArrayModel, Directions, Model};
+    use crate::replay::{Recorder, Replay, Spawn};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    mod turn {
+        use super::*;
+
+        #[test]
+        fn corners() {
+            assert_eq!("a1:2".parse::<Turn>().unwrap().spawn.square, 0);
+            assert_eq!("d1:2".parse::<Turn>().unwrap().spawn.square, 3);
+            assert_eq!("a4:2".parse::<Turn>().unwrap().spawn.square, 12);
+            assert_eq!("d4:2".parse::<Turn>().unwrap().spawn.square, 15);
+        }
+
+        #[test]
+        fn every_direction_round_trips() {
+            for direction in [
+                Directions::Up,
+                Directions::Right,
+                Directions::Down,
+                Directions::Left,
+            ]
+            .iter()
+            {
+                let turn = Turn {
+                    direction: Some(*direction),
+                    spawn: Spawn {
+                        square: 6,
+                        exponent: 2,
+                    },
+                };
+
+                assert_eq!(turn.to_string().parse::<Turn>().unwrap(), turn);
+            }
+        }
+
+        #[test]
+        fn formats_numbers_too_large_for_u64() {
+            let turn = |exponent| Turn {
+                direction: None,
+                spawn: Spawn {
+                    square: 0,
+                    exponent,
+                },
+            };
+
+            assert_eq!(turn(63).to_string(), "a1:9223372036854775808");
+            assert_eq!(turn(64).to_string(), "a1:18446744073709551616");
+            assert_eq!(turn(100).to_string(), "a1:1267650600228229401496703205376");
+            assert!(turn(255).to_string().parse::<Turn>().is_err());
+        }
+
+        #[test]
+        fn rejects_invalid_turns() {
+            for text in [
+                "", "l b3:2", "L", "L b3", "L e1:2", "L a5:2", "L a0:2", "L a1:3", "L a1:1",
+                "L a1:x", "L a12:2", "L b3:2 R",
+            ]
+            .iter()
+            {
+                assert!(text.parse::<Turn>().is_err(), "{:?} parsed", text);
+            }
+        }
+    }
+
+    mod parse_turns {
+        use super::*;
+
+        #[test]
+        fn reports_the_line_of_the_error() {
+            let error = parse_turns("# Opening\na1:2\n\nb1:2\nL a1").unwrap_err();
+
+            assert_eq!(error.line, 5);
+            assert_eq!(error.text, "L a1");
+        }
+    }
+
+    mod format_turns {
+        use super::*;
+
+        #[test]
+        fn recorded_games_round_trip() {
+            let mut rng = StdRng::seed_from_u64(8);
+            let mut agent = PreferenceOrder::corner();
+            let mut recorder = Recorder::new(ArrayModel::new());
+            recorder.random(&mut rng).unwrap();
+            recorder.random(&mut rng).unwrap();
+            while let Some(direction) = agent.choose(recorder.game()) {
+                if recorder.slide(direction).is_none() {
+                    break;
+                }
+                recorder.random(&mut rng).unwrap();
+            }
+            let replay = recorder.finish();
+
+            let text = format_turns(&replay.turns().unwrap());
+            let parsed = Replay::from_turns(&parse_turns(&text).unwrap());
+
+            assert_eq!(parsed, replay);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use rand::Rng;
+
+use super::{Replay, Spawn, Spawns};
+use crate::base::*;
+
+/// Records a game played by hand into a [`Replay`]
+///
+/// The recorder wraps a game and remembers every move and every spawn made
+/// through it, so games that are not played from a seed can be replayed too.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::models::{ArrayModel, Directions, Model};
+/// use game_2048_model::replay::Recorder;
+///
+/// let mut rng = rand::thread_rng();
+/// let mut recorder = Recorder::new(ArrayModel::new());
+/// recorder.random(&mut rng).unwrap();
+/// recorder.random(&mut rng).unwrap();
+///
+/// for direction in [Directions::Left, Directions::Down, Directions::Right].iter() {
+///     if recorder.slide(*direction).is_some() {
+///         recorder.random(&mut rng).unwrap();
+///     }
+/// }
+///
+/// let replay = recorder.finish();
+/// let game = replay.simulate::<ArrayModel>().unwrap();
+/// assert_eq!(game.as_array(), recorder.game().as_array());
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct Recorder<M: Model> {
+    game: M,
+    initial: ArrayBoard,
+    spawns: Vec<Spawn>,
+    moves: Vec<Directions>,
+    score: u64,
+}
+
+impl<M: Model> Recorder<M> {
+    /// Start recording from the current board of the game
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::models::{ArrayModel, Model};
+    /// use game_2048_model::replay::Recorder;
+    ///
+    /// let recorder = Recorder::new(ArrayModel::new());
+    /// ```
+    ///
+    pub fn new(game: M) -> Self {
+        Recorder {
+            initial: game.as_array(),
+            game,
+            spawns: Vec::new(),
+            moves: Vec::new(),
+            score: 0,
+        }
+    }
+
+    /// The game being recorded
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::models::{ArrayModel, Model};
+    /// use game_2048_model::replay::Recorder;
+    ///
+    /// let recorder = Recorder::new(ArrayModel::new());
+    /// assert_eq!(recorder.game().as_array(), [0; 16]);
+    /// ```
+    ///
+    pub fn game(&self) -> &M {
+        &self.game
+    }
+
+    /// Slide the game and record the move if it changes the board
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::models::{ArrayModel, Directions};
+    /// use game_2048_model::replay::Recorder;
+    ///
+    /// let mut recorder = Recorder::new(ArrayModel::from([0; 16]));
+    /// assert_eq!(recorder.slide(Directions::Up), None);
+    /// ```
+    ///
+    pub fn slide(&mut self, direction: Directions) -> Option<bool> {
+        let before = self.game.as_array();
+        let result = self.game.slide(direction);
+        if result.is_some() {
+            self.score += slide_score(&before, &self.game.as_array());
+            self.moves.push(direction);
+        }
+        result
+    }
+
+    /// Spawn a random number on the game and record it
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::models::ArrayModel;
+    /// use game_2048_model::replay::Recorder;
+    ///
+    /// let mut recorder = Recorder::new(ArrayModel::from([0; 16]));
+    /// recorder.random(&mut rand::thread_rng()).unwrap();
+    /// ```
+    ///
+    pub fn random<R: Rng>(&mut self, rng: &mut R) -> Result<(), NoEmptyError> {
+        let before = self.game.as_array();
+        self.game.random(rng)?;
+        let after = self.game.as_array();
+        if let Some(square) = (0..after.len()).find(|i| before[*i] != after[*i]) {
+            self.spawns.push(Spawn {
+                square,
+                exponent: after[square],
+            });
+        }
+        Ok(())
+    }
+
+    /// The replay of everything recorded so far
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::models::{ArrayModel, Model};
+    /// use game_2048_model::replay::{Recorder, Spawns};
+    ///
+    /// let replay = Recorder::new(ArrayModel::new()).finish();
+    /// assert_eq!(replay.spawns, Spawns::Explicit(Vec::new()));
+    /// ```
+    ///
+    pub fn finish(&self) -> Replay {
+        Replay {
+            initial: self.initial,
+            spawns: Spawns::Explicit(self.spawns.clone()),
+            moves: self.moves.clone(),
+            score: self.score,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Recorder;
+    use crate::models::{ArrayModel, Directions, Model};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    mod finish {
+        use super::*;
+
+        #[test]
+        fn replays_to_the_recorded_game() {
+            let mut rng = StdRng::seed_from_u64(3);
+            let mut recorder = Recorder::new(ArrayModel::new());
+            recorder.random(&mut rng).unwrap();
+            recorder.random(&mut rng).unwrap();
+            for turn in 0..200 {
+                let direction = [Directions::Down, Directions::Left, Directions::Right][turn % 3];
+                if recorder.slide(direction).is_some() {
+                    recorder.random(&mut rng).unwrap();
+                }
+            }
+
+            let game = recorder.finish().simulate::<ArrayModel>().unwrap();
+            assert_eq!(game.as_array(), recorder.game().as_array());
+        }
+    }
+}
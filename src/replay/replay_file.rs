@@ -0,0 +1,562 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use super::ReplayError;
+use crate::ai::{play_moves, Agent};
+use crate::base::*;
+
+const MAGIC: &[u8; 4] = b"RPLY";
+const VERSION: u32 = 1;
+
+/// A number placed on the board after a move
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Spawn {
+    /// The square of the number, indexed like an [`ArrayBoard`]
+    pub square: usize,
+    /// The exponent of the number
+    pub exponent: BoardElement,
+}
+
+/// Where the numbers spawned during a replay come from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Spawns {
+    /// Spawned by [`Model::random`] with a [`StdRng`] seeded with the value
+    Seed(u64),
+    /// Every spawn in the order they were placed
+    Explicit(Vec<Spawn>),
+}
+
+/// A recorded game
+///
+/// A replay starting from an empty board begins with two spawns, like a new
+/// game, and every move is followed by a spawn.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::models::{ArrayModel, Directions, Model};
+/// use game_2048_model::replay::{Replay, Spawn, Spawns};
+///
+/// let replay = Replay {
+///     initial: [0; 16],
+///     spawns: Spawns::Explicit(vec![
+///         Spawn { square: 0, exponent: 1 },
+///         Spawn { square: 1, exponent: 1 },
+///         Spawn { square: 15, exponent: 2 },
+///     ]),
+///     moves: vec![Directions::Left],
+///     score: 4,
+/// };
+///
+/// let game = replay.simulate::<ArrayModel>().unwrap();
+/// assert_eq!(game.as_array()[0], 2);
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    /// The board before the first spawn
+    pub initial: ArrayBoard,
+    /// The numbers spawned during the game
+    pub spawns: Spawns,
+    /// Every move in the order they were played
+    pub moves: Vec<Directions>,
+    /// The score gained by the merges of the moves
+    pub score: u64,
+}
+
+impl Replay {
+    /// Play a new game from a seed with an agent and record it
+    ///
+    /// The game is the same one [`play_game`](crate::ai::play_game) plays with
+    /// the same agent and seed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::{play_game, Greedy};
+    /// use game_2048_model::models::ArrayModel;
+    /// use game_2048_model::replay::{Replay, Spawns};
+    ///
+    /// let replay = Replay::record::<ArrayModel, _>(&mut Greedy, 7);
+    /// let result = play_game::<ArrayModel, _>(&mut Greedy, 7);
+    ///
+    /// assert_eq!(replay.spawns, Spawns::Seed(7));
+    /// assert_eq!(replay.moves.len(), result.moves);
+    /// assert_eq!(replay.score, result.score);
+    /// ```
+    ///
+    pub fn record<M: Model, A: Agent<M> + ?Sized>(agent: &mut A, seed: u64) -> Self {
+        let mut moves = Vec::new();
+        let result = play_moves::<M, _, _>(agent, seed, |direction| moves.push(direction));
+
+        Replay {
+            initial: [0; BOARD_SIZE * BOARD_SIZE],
+            spawns: Spawns::Seed(seed),
+            moves,
+            score: result.score,
+        }
+    }
+
+    /// Play the replay again and return the final game
+    ///
+    /// Fails if a move does not change the board, a spawn is not on an empty
+    /// square or not a number that can spawn, the number of spawns does not
+    /// match the moves, or the score does not match.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::models::{ArrayModel, Directions};
+    /// use game_2048_model::replay::{Replay, ReplayError, Spawns};
+    ///
+    /// let replay = Replay {
+    ///     initial: [0; 16],
+    ///     spawns: Spawns::Seed(1),
+    ///     moves: Vec::new(),
+    ///     score: 100,
+    /// };
+    ///
+    /// assert_eq!(
+    ///     replay.simulate::<ArrayModel>().err(),
+    ///     Some(ReplayError::ScoreMismatch { claimed: 100, actual: 0 })
+    /// );
+    /// ```
+    ///
+    pub fn simulate<M: Model>(&self) -> Result<M, ReplayError> {
+        let mut game = M::from(self.initial);
+        let initial_spawns = if self.initial.iter().all(|x| *x == 0) {
+            2
+        } else {
+            0
+        };
+
+        let mut spawner = match &self.spawns {
+            Spawns::Seed(seed) => Spawner::Seed(Box::new(StdRng::seed_from_u64(*seed))),
+            Spawns::Explicit(spawns) => {
+                let expected = initial_spawns + self.moves.len();
+                if spawns.len() != expected {
+                    return Err(ReplayError::SpawnCount {
+                        expected,
+                        found: spawns.len(),
+                    });
+                }
+                Spawner::Explicit(spawns, 0)
+            }
+        };
+
+        for _ in 0..initial_spawns {
+            spawner.spawn(&mut game)?;
+        }
+        let mut score = 0;
+        for (turn, direction) in self.moves.iter().enumerate() {
+            let before = game.as_array();
+            if game.slide(*direction).is_none() {
+                return Err(ReplayError::IllegalMove {
+                    turn,
+                    direction: *direction,
+                });
+            }
+            score += slide_score(&before, &game.as_array());
+            spawner.spawn(&mut game)?;
+        }
+
+        if score != self.score {
+            return Err(ReplayError::ScoreMismatch {
+                claimed: self.score,
+                actual: score,
+            });
+        }
+        Ok(game)
+    }
+
+    /// Write the replay in the binary replay format
+    ///
+    /// The header holds the format version, the board size and the spawn
+    /// probabilities, so replays of other rules are rejected when read.
+    /// Fails with [`io::ErrorKind::InvalidInput`] if there are more than
+    /// `u32::MAX` spawns or moves, or a spawn square does not fit in a byte.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::ai::Greedy;
+    /// use game_2048_model::models::ArrayModel;
+    /// use game_2048_model::replay::Replay;
+    ///
+    /// let replay = Replay::record::<ArrayModel, _>(&mut Greedy, 3);
+    /// let mut buffer = Vec::new();
+    /// replay.write(&mut buffer).unwrap();
+    ///
+    /// assert_eq!(&buffer[..4], b"RPLY");
+    /// ```
+    ///
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        fn count(length: usize) -> io::Result<[u8; 4]> {
+            u32::try_from(length)
+                .map(u32::to_le_bytes)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Too many entries"))
+        }
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&[BOARD_SIZE as u8, SPAWN_PROBABILITIES.len() as u8])?;
+        for (exponent, probability) in SPAWN_PROBABILITIES.iter() {
+            writer.write_all(&[*exponent])?;
+            writer.write_all(&probability.to_le_bytes())?;
+        }
+        writer.write_all(&self.initial)?;
+
+        match &self.spawns {
+            Spawns::Seed(seed) => {
+                writer.write_all(&[0])?;
+                writer.write_all(&seed.to_le_bytes())?;
+            }
+            Spawns::Explicit(spawns) => {
+                writer.write_all(&[1])?;
+                writer.write_all(&count(spawns.len())?)?;
+                for spawn in spawns.iter() {
+                    let square = u8::try_from(spawn.square).map_err(|_| {
+                        io::Error::new(io::ErrorKind::InvalidInput, "Spawn square out of range")
+                    })?;
+                    writer.write_all(&[square, spawn.exponent])?;
+                }
+            }
+        }
+
+        writer.write_all(&count(self.moves.len())?)?;
+        for direction in self.moves.iter() {
+            writer.write_all(&[direction_index(*direction)])?;
+        }
+        writer.write_all(&self.score.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// Read a replay written by [`Replay::write`]
+    ///
+    /// The replay is not simulated, use [`Replay::simulate`] to validate it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::replay::Replay;
+    ///
+    /// assert!(Replay::read(&mut "not a replay".as_bytes()).is_err());
+    /// ```
+    ///
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        fn read<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+            let mut bytes = [0; N];
+            reader.read_exact(&mut bytes)?;
+            Ok(bytes)
+        }
+        fn invalid(message: &str) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        }
+
+        if &read::<R, 4>(reader)? != MAGIC {
+            return Err(invalid("Not a replay"));
+        }
+        if u32::from_le_bytes(read(reader)?) != VERSION {
+            return Err(invalid("Unsupported replay version"));
+        }
+        let [size, rules] = read::<R, 2>(reader)?;
+        if usize::from(size) != BOARD_SIZE {
+            return Err(invalid("Unsupported board size"));
+        }
+        let mut spawn_probabilities = Vec::new();
+        for _ in 0..rules {
+            let [exponent] = read::<R, 1>(reader)?;
+            let probability = f64::from_le_bytes(read(reader)?);
+            spawn_probabilities.push((exponent, probability));
+        }
+        if spawn_probabilities != SPAWN_PROBABILITIES {
+            return Err(invalid("Unsupported spawn rules"));
+        }
+        let initial: ArrayBoard = read(reader)?;
+
+        let spawns = match read::<R, 1>(reader)? {
+            [0] => Spawns::Seed(u64::from_le_bytes(read(reader)?)),
+            [1] => {
+                let count = u32::from_le_bytes(read(reader)?);
+                let mut spawns = Vec::new();
+                for _ in 0..count {
+                    let [square, exponent] = read::<R, 2>(reader)?;
+                    spawns.push(Spawn {
+                        square: usize::from(square),
+                        exponent,
+                    });
+                }
+                Spawns::Explicit(spawns)
+            }
+            _ => return Err(invalid("Unknown kind of spawns")),
+        };
+
+        let count = u32::from_le_bytes(read(reader)?);
+        let mut moves = Vec::new();
+        for _ in 0..count {
+            let [index] = read::<R, 1>(reader)?;
+            let direction = DIRECTIONS
+                .get(usize::from(index))
+                .ok_or_else(|| invalid("Unknown direction"))?;
+            moves.push(*direction);
+        }
+        let score = u64::from_le_bytes(read(reader)?);
+
+        Ok(Replay {
+            initial,
+            spawns,
+            moves,
+            score,
+        })
+    }
+}
+
+/// The index of the direction in [`DIRECTIONS`]
+fn direction_index(direction: Directions) -> u8 {
+    DIRECTIONS
+        .iter()
+        .position(|other| *other == direction)
+        .expect("Every direction is in DIRECTIONS") as u8
+}
+
+/// Places the spawns of a replay one by one
+enum Spawner<'a> {
+    Seed(Box<StdRng>),
+    Explicit(&'a [Spawn], usize),
+}
+
+impl Spawner<'_> {
+    fn spawn<M: Model>(&mut self, game: &mut M) -> Result<(), ReplayError> {
+        match self {
+            Spawner::Seed(rng) => {
+                // A legal move always leaves an empty square to spawn on
+                let _ = game.random(rng);
+                Ok(())
+            }
+            Spawner::Explicit(spawns, next) => {
+                let index = *next;
+                let spawn = spawns[index];
+                *next += 1;
+
+                let mut board = game.as_array();
+                if spawn.square >= board.len()
+                    || SPAWN_PROBABILITIES
+                        .iter()
+                        .all(|(exponent, _)| *exponent != spawn.exponent)
+                {
+                    return Err(ReplayError::InvalidSpawn { spawn: index });
+                }
+                if board[spawn.square] != 0 {
+                    return Err(ReplayError::OccupiedSquare {
+                        spawn: index,
+                        square: spawn.square,
+                    });
+                }
+                board[spawn.square] = spawn.exponent;
+                *game = M::from(board);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Replay, Spawn, Spawns};
+    use crate::ai::{Greedy, PreferenceOrder};
+    use crate::models::{ArrayModel, Directions, Model};
+    use crate::replay::ReplayError;
+
+    fn explicit() -> Replay {
+        Replay {
+            initial: [0; 16],
+            spawns: Spawns::Explicit(vec![
+                Spawn {
+                    square: 0,
+                    exponent: 1,
+                },
+                Spawn {
+                    square: 1,
+                    exponent: 1,
+                },
+                Spawn {
+                    square: 12,
+                    exponent: 1,
+                },
+                Spawn {
+                    square: 14,
+                    exponent: 1,
+                },
+            ]),
+            moves: vec![Directions::Left, Directions::Down],
+            score: 4,
+        }
+    }
+
+    mod simulate {
+        use super::*;
+
+        #[test]
+        fn recorded_games_simulate() {
+            let replay = Replay::record::<ArrayModel, _>(&mut PreferenceOrder::corner(), 11);
+
+            assert!(replay.simulate::<ArrayModel>().is_ok());
+        }
+
+        #[test]
+        fn explicit_spawns_simulate() {
+            let game = explicit().simulate::<ArrayModel>().unwrap();
+
+            assert_eq!(
+                game.as_array(),
+                [0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 1, 0, 1, 0]
+            );
+        }
+
+        #[test]
+        fn starts_from_a_given_board() {
+            let mut initial = [0; 16];
+            initial[0] = 3;
+            initial[1] = 3;
+            let replay = Replay {
+                initial,
+                spawns: Spawns::Explicit(vec![Spawn {
+                    square: 15,
+                    exponent: 1,
+                }]),
+                moves: vec![Directions::Left],
+                score: 16,
+            };
+
+            assert!(replay.simulate::<ArrayModel>().is_ok());
+        }
+
+        #[test]
+        fn rejects_illegal_moves() {
+            let mut replay = explicit();
+            replay.moves[1] = Directions::Left;
+
+            assert_eq!(
+                replay.simulate::<ArrayModel>().err(),
+                Some(ReplayError::IllegalMove {
+                    turn: 1,
+                    direction: Directions::Left
+                })
+            );
+        }
+
+        #[test]
+        fn rejects_spawns_on_occupied_squares() {
+            let mut replay = explicit();
+            if let Spawns::Explicit(spawns) = &mut replay.spawns {
+                spawns[1].square = 0;
+            }
+
+            assert_eq!(
+                replay.simulate::<ArrayModel>().err(),
+                Some(ReplayError::OccupiedSquare {
+                    spawn: 1,
+                    square: 0
+                })
+            );
+        }
+
+        #[test]
+        fn rejects_numbers_that_cannot_spawn() {
+            let mut replay = explicit();
+            if let Spawns::Explicit(spawns) = &mut replay.spawns {
+                spawns[2].exponent = 5;
+            }
+
+            assert_eq!(
+                replay.simulate::<ArrayModel>().err(),
+                Some(ReplayError::InvalidSpawn { spawn: 2 })
+            );
+        }
+
+        #[test]
+        fn rejects_missing_spawns() {
+            let mut replay = explicit();
+            replay.moves.push(Directions::Up);
+
+            assert_eq!(
+                replay.simulate::<ArrayModel>().err(),
+                Some(ReplayError::SpawnCount {
+                    expected: 5,
+                    found: 4
+                })
+            );
+        }
+    }
+
+    mod record {
+        use super::*;
+        use crate::ai::play_game;
+
+        #[test]
+        fn plays_the_same_game_as_play_game() {
+            let replay = Replay::record::<ArrayModel, _>(&mut PreferenceOrder::corner(), 21);
+            let result = play_game::<ArrayModel, _>(&mut PreferenceOrder::corner(), 21);
+            let game = replay.simulate::<ArrayModel>().unwrap();
+
+            assert_eq!(replay.moves.len(), result.moves);
+            assert_eq!(replay.score, result.score);
+            assert_eq!(game.as_array().iter().max(), Some(&result.max_exponent));
+        }
+    }
+
+    mod write {
+        use super::*;
+        use std::io::ErrorKind;
+
+        #[test]
+        fn rejects_squares_that_do_not_fit_in_a_byte() {
+            let mut replay = explicit();
+            if let Spawns::Explicit(spawns) = &mut replay.spawns {
+                spawns[0].square = 256;
+            }
+
+            let error = replay.write(&mut Vec::new()).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        }
+    }
+
+    mod read {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            for replay in [explicit(), Replay::record::<ArrayModel, _>(&mut Greedy, 5)] {
+                let mut buffer = Vec::new();
+                replay.write(&mut buffer).unwrap();
+
+                assert_eq!(Replay::read(&mut buffer.as_slice()).unwrap(), replay);
+            }
+        }
+
+        #[test]
+        fn rejects_other_versions() {
+            let mut buffer = Vec::new();
+            explicit().write(&mut buffer).unwrap();
+            buffer[4] = 2;
+
+            assert!(Replay::read(&mut buffer.as_slice()).is_err());
+        }
+
+        #[test]
+        fn rejects_other_spawn_rules() {
+            let mut buffer = Vec::new();
+            explicit().write(&mut buffer).unwrap();
+            // The first spawn exponent follows the magic, version, size and rule count
+            buffer[10] = 3;
+
+            assert!(Replay::read(&mut buffer.as_slice()).is_err());
+        }
+    }
+}
@@ -0,0 +1,245 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use super::{Replay, ReplayError};
+use crate::base::*;
+
+/// The exponent of 131072, the largest number a game can build
+const MAX_EXPONENT: BoardElement = 17;
+
+/// The outcome of a replay accepted by a [`Verifier`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Verified {
+    /// The board at the end of the replay
+    pub board: ArrayBoard,
+    /// The score of the replay, equal to the claimed score
+    pub score: u64,
+    /// The number of moves played
+    pub moves: usize,
+    /// The exponent of the largest number on the final board
+    pub max_exponent: BoardElement,
+}
+
+/// Checks submitted games before they are trusted
+///
+/// The replay is played again with the game model given to
+/// [`Verifier::verify`], so it is checked by the exact engine used to play it.
+/// Every move has to change the board, every spawn has to be a number that
+/// can spawn on an empty square and the claimed score has to be the score of
+/// the moves. By default the game also has to start from an empty board.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::ai::PreferenceOrder;
+/// use game_2048_model::models::ArrayModel;
+/// use game_2048_model::replay::{Replay, ReplayError, Verifier};
+///
+/// let mut replay = Replay::record::<ArrayModel, _>(&mut PreferenceOrder::corner(), 5);
+/// let verifier = Verifier::new().require_game_over(true);
+/// assert!(verifier.verify::<ArrayModel>(&replay).is_ok());
+///
+/// replay.score += 1000;
+/// assert!(matches!(
+///     verifier.verify::<ArrayModel>(&replay),
+///     Err(ReplayError::ScoreMismatch { .. })
+/// ));
+/// ```
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Verifier {
+    empty_start: bool,
+    game_over: bool,
+}
+
+impl Default for Verifier {
+    fn default() -> Self {
+        Verifier {
+            empty_start: true,
+            game_over: false,
+        }
+    }
+}
+
+impl Verifier {
+    /// Create a verifier requiring an empty starting board
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::replay::Verifier;
+    ///
+    /// let verifier = Verifier::new();
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether the game has to start from an empty board
+    ///
+    /// Games starting from another board are still checked to only hold
+    /// numbers a game can build.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::replay::Verifier;
+    ///
+    /// // Accept puzzles starting from a given board
+    /// let verifier = Verifier::new().require_empty_start(false);
+    /// ```
+    ///
+    pub fn require_empty_start(mut self, empty_start: bool) -> Self {
+        self.empty_start = empty_start;
+        self
+    }
+
+    /// Set whether the game has to end with no move left
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::replay::Verifier;
+    ///
+    /// // Only accept finished games on a leaderboard
+    /// let verifier = Verifier::new().require_game_over(true);
+    /// ```
+    ///
+    pub fn require_game_over(mut self, game_over: bool) -> Self {
+        self.game_over = game_over;
+        self
+    }
+
+    /// Play the replay again and check it
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::models::{ArrayModel, Directions};
+    /// use game_2048_model::replay::{Replay, ReplayError, Spawns, Verifier};
+    ///
+    /// let mut initial = [0; 16];
+    /// initial[0] = 1;
+    /// let replay = Replay {
+    ///     initial,
+    ///     spawns: Spawns::Explicit(Vec::new()),
+    ///     moves: Vec::new(),
+    ///     score: 0,
+    /// };
+    ///
+    /// assert_eq!(
+    ///     Verifier::new().verify::<ArrayModel>(&replay),
+    ///     Err(ReplayError::InvalidStart)
+    /// );
+    /// ```
+    ///
+    pub fn verify<M: Model>(&self, replay: &Replay) -> Result<Verified, ReplayError> {
+        let invalid_number = replay.initial.iter().any(|x| *x > MAX_EXPONENT);
+        let empty = replay.initial.iter().all(|x| *x == 0);
+        if invalid_number || (self.empty_start && !empty) {
+            return Err(ReplayError::InvalidStart);
+        }
+
+        let board = replay.simulate::<M>()?.as_array();
+        if self.game_over
+            && DIRECTIONS
+                .iter()
+                .any(|direction| M::from(board).slide(*direction).is_some())
+        {
+            return Err(ReplayError::NotOver);
+        }
+
+        Ok(Verified {
+            board,
+            score: replay.score,
+            moves: replay.moves.len(),
+            max_exponent: board.iter().copied().max().unwrap_or(0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Verifier;
+    use crate::ai::Greedy;
+    use crate::models::{ArrayModel, Directions};
+    use crate::replay::{Replay, ReplayError, Spawn, Spawns};
+
+    mod verify {
+        use super::*;
+
+        #[test]
+        fn accepts_recorded_games() {
+            let replay = Replay::record::<ArrayModel, _>(&mut Greedy, 21);
+            let verified = Verifier::new()
+                .require_game_over(true)
+                .verify::<ArrayModel>(&replay)
+                .unwrap();
+
+            assert_eq!(verified.score, replay.score);
+            assert_eq!(verified.moves, replay.moves.len());
+        }
+
+        #[test]
+        fn rejects_unfinished_games() {
+            let replay = Replay {
+                initial: [0; 16],
+                spawns: Spawns::Explicit(vec![
+                    Spawn {
+                        square: 0,
+                        exponent: 1,
+                    },
+                    Spawn {
+                        square: 5,
+                        exponent: 2,
+                    },
+                ]),
+                moves: Vec::new(),
+                score: 0,
+            };
+
+            assert!(Verifier::new().verify::<ArrayModel>(&replay).is_ok());
+            assert_eq!(
+                Verifier::new()
+                    .require_game_over(true)
+                    .verify::<ArrayModel>(&replay),
+                Err(ReplayError::NotOver)
+            );
+        }
+
+        #[test]
+        fn rejects_tampered_moves() {
+            let mut replay = Replay::record::<ArrayModel, _>(&mut Greedy, 4);
+            let last = replay.moves.len() - 1;
+            replay.moves.push(replay.moves[last]);
+
+            assert!(matches!(
+                Verifier::new().verify::<ArrayModel>(&replay),
+                Err(ReplayError::IllegalMove { .. })
+            ));
+        }
+
+        #[test]
+        fn rejects_numbers_the_board_cannot_hold() {
+            let mut initial = [0; 16];
+            initial[15] = 18;
+            let replay = Replay {
+                initial,
+                spawns: Spawns::Explicit(vec![Spawn {
+                    square: 0,
+                    exponent: 1,
+                }]),
+                moves: vec![Directions::Left],
+                score: 0,
+            };
+
+            assert_eq!(
+                Verifier::new()
+                    .require_empty_start(false)
+                    .verify::<ArrayModel>(&replay),
+                Err(ReplayError::InvalidStart)
+            );
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! JSON game server, enabled by the `server` feature
+//!
+//! [`Sessions`] keeps games in memory and answers requests of a small JSON
+//! API, so web and mobile frontends can play with this engine instead of
+//! reimplementing the rules. The `server` binary serves it over HTTP on the
+//! local machine.
+//!
+//! ```
+//! use game_2048_model::server::Sessions;
+//!
+//! let mut sessions = Sessions::new();
+//! let response = sessions.handle("POST", "/games", r#"{"seed": 1}"#);
+//!
+//! assert_eq!(response.status, 201);
+//! assert!(response.body.contains("\"score\":0"));
+//! ```
+
+mod sessions;
+
+pub use sessions::{Response, Sessions};
@@ -0,0 +1,443 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::base::*;
+use crate::environment::Environment;
+use crate::models::ArrayModel;
+
+/// The number of games held by [`Sessions::new`]
+const MAX_GAMES: usize = 1000;
+
+/// A status code and JSON body to send back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// The HTTP status code
+    pub status: u16,
+    /// The JSON body, empty for responses without content
+    pub body: String,
+}
+
+impl Response {
+    fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        Response {
+            status,
+            body: serde_json::to_string(value).expect("Game states are always serializable"),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Response::json(status, &ErrorBody { error: message })
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct NewGame {
+    seed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Move {
+    direction: String,
+}
+
+/// A game as it is sent to clients
+#[derive(Serialize)]
+struct GameState {
+    id: u64,
+    seed: u64,
+    /// The numbers on the board row by row, 0 for empty squares
+    board: Vec<Vec<u64>>,
+    score: u64,
+    moves: usize,
+    max_tile: u64,
+    over: bool,
+    /// The directions that change the board
+    legal: Vec<&'static str>,
+}
+
+struct Session {
+    seed: u64,
+    environment: Environment<ArrayModel>,
+    history: Vec<Environment<ArrayModel>>,
+    /// The request count when the game was last used
+    last_used: u64,
+}
+
+fn direction_name(direction: Directions) -> &'static str {
+    match direction {
+        Directions::Up => "up",
+        Directions::Right => "right",
+        Directions::Down => "down",
+        Directions::Left => "left",
+    }
+}
+
+/// Games held in memory and the JSON API to play them
+///
+/// Requests are handled without any network, the server binary only passes
+/// them on. The routes are:
+///
+/// - `POST /games` with an optional body `{"seed": 42}` creates a game
+/// - `GET /games/{id}` returns the game
+/// - `POST /games/{id}/moves` with a body `{"direction": "left"}` plays a move
+/// - `POST /games/{id}/undo` takes back the last move
+/// - `DELETE /games/{id}` forgets the game
+///
+/// Every route returning a game sends its id, seed, board as rows of numbers,
+/// score, number of moves, largest number, whether it is over and the
+/// legal directions. Errors are sent as `{"error": "..."}`.
+///
+/// At most 1000 games are held by default. Creating a game beyond the limit
+/// forgets the game that was used least recently.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::server::Sessions;
+///
+/// let mut sessions = Sessions::new();
+/// let created = sessions.handle("POST", "/games", r#"{"seed": 42}"#);
+/// assert_eq!(created.status, 201);
+///
+/// let moved = sessions.handle("POST", "/games/1/moves", r#"{"direction": "up"}"#);
+/// assert!(moved.status == 200 || moved.status == 422);
+/// ```
+///
+pub struct Sessions {
+    games: HashMap<u64, Session>,
+    next_id: u64,
+    max_games: usize,
+    requests: u64,
+}
+
+impl Default for Sessions {
+    fn default() -> Self {
+        Sessions {
+            games: HashMap::new(),
+            next_id: 0,
+            max_games: MAX_GAMES,
+            requests: 0,
+        }
+    }
+}
+
+impl Sessions {
+    /// Create an empty set of games
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::server::Sessions;
+    ///
+    /// let sessions = Sessions::new();
+    /// assert!(sessions.is_empty());
+    /// ```
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of games held before the least recently used one is forgotten, at least 1
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::server::Sessions;
+    ///
+    /// let mut sessions = Sessions::new().max_games(1);
+    /// sessions.handle("POST", "/games", "");
+    /// sessions.handle("POST", "/games", "");
+    ///
+    /// assert_eq!(sessions.len(), 1);
+    /// assert_eq!(sessions.handle("GET", "/games/1", "").status, 404);
+    /// ```
+    ///
+    pub fn max_games(mut self, max_games: usize) -> Self {
+        self.max_games = max_games.max(1);
+        self
+    }
+
+    /// The number of games held
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::server::Sessions;
+    ///
+    /// let mut sessions = Sessions::new();
+    /// sessions.handle("POST", "/games", "");
+    /// assert_eq!(sessions.len(), 1);
+    /// ```
+    ///
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    /// Whether no game is held
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::server::Sessions;
+    ///
+    /// assert!(Sessions::new().is_empty());
+    /// ```
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    /// Handle a request and return the response
+    ///
+    /// The url may have a query string, which is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::server::Sessions;
+    ///
+    /// let mut sessions = Sessions::new();
+    ///
+    /// assert_eq!(sessions.handle("GET", "/games/7", "").status, 404);
+    /// ```
+    ///
+    pub fn handle(&mut self, method: &str, url: &str, body: &str) -> Response {
+        self.requests += 1;
+        let path = url.split('?').next().unwrap_or("");
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+        match (method, segments.as_slice()) {
+            ("POST", ["games"]) => self.create(body),
+            (_, ["games"]) => Response::error(405, "Use POST to create a game"),
+            (method, ["games", id, rest @ ..]) => {
+                let id = match id.parse::<u64>() {
+                    Ok(id) if self.games.contains_key(&id) => id,
+                    _ => return Response::error(404, "No game with this id"),
+                };
+                self.games
+                    .get_mut(&id)
+                    .expect("The id was checked")
+                    .last_used = self.requests;
+                match (method, rest) {
+                    ("GET", []) => self.state(id, 200),
+                    ("DELETE", []) => {
+                        self.games.remove(&id);
+                        Response {
+                            status: 204,
+                            body: String::new(),
+                        }
+                    }
+                    ("POST", ["moves"]) => self.slide(id, body),
+                    ("POST", ["undo"]) => self.undo(id),
+                    (_, []) | (_, ["moves"]) | (_, ["undo"]) => {
+                        Response::error(405, "Method not allowed")
+                    }
+                    _ => Response::error(404, "Unknown route"),
+                }
+            }
+            _ => Response::error(404, "Unknown route"),
+        }
+    }
+
+    fn create(&mut self, body: &str) -> Response {
+        let request: NewGame = if body.trim().is_empty() {
+            NewGame::default()
+        } else {
+            match serde_json::from_str(body) {
+                Ok(request) => request,
+                Err(error) => return Response::error(400, &error.to_string()),
+            }
+        };
+        let seed = request.seed.unwrap_or_else(rand::random);
+
+        let mut environment = Environment::new();
+        environment.reset(seed);
+        if self.games.len() >= self.max_games {
+            let oldest = self
+                .games
+                .iter()
+                .min_by_key(|(_, session)| session.last_used)
+                .map(|(id, _)| *id);
+            if let Some(id) = oldest {
+                self.games.remove(&id);
+            }
+        }
+        self.next_id += 1;
+        self.games.insert(
+            self.next_id,
+            Session {
+                seed,
+                environment,
+                history: Vec::new(),
+                last_used: self.requests,
+            },
+        );
+        self.state(self.next_id, 201)
+    }
+
+    fn slide(&mut self, id: u64, body: &str) -> Response {
+        let request: Move = match serde_json::from_str(body) {
+            Ok(request) => request,
+            Err(error) => return Response::error(400, &error.to_string()),
+        };
+        let direction = match DIRECTIONS
+            .iter()
+            .find(|d| direction_name(**d) == request.direction.to_lowercase())
+        {
+            Some(direction) => *direction,
+            None => return Response::error(400, "The direction must be up, right, down or left"),
+        };
+
+        let session = self.games.get_mut(&id).expect("The id was checked");
+        let before = session.environment.clone();
+        if session.environment.step(direction).info.invalid_move {
+            return Response::error(422, "The move does not change the board");
+        }
+        session.history.push(before);
+        self.state(id, 200)
+    }
+
+    fn undo(&mut self, id: u64) -> Response {
+        let session = self.games.get_mut(&id).expect("The id was checked");
+        match session.history.pop() {
+            Some(previous) => {
+                session.environment = previous;
+                self.state(id, 200)
+            }
+            None => Response::error(409, "There is no move to undo"),
+        }
+    }
+
+    fn state(&self, id: u64, status: u16) -> Response {
+        let session = &self.games[&id];
+        let board = session.environment.observation();
+        let value = |x: &BoardElement| if *x == 0 { 0 } else { 1u64 << x };
+        let legal = session.environment.legal_actions();
+
+        Response::json(
+            status,
+            &GameState {
+                id,
+                seed: session.seed,
+                board: board
+                    .chunks(BOARD_SIZE)
+                    .map(|row| row.iter().map(value).collect())
+                    .collect(),
+                score: session.environment.score(),
+                moves: session.history.len(),
+                max_tile: board.iter().map(value).max().unwrap_or(0),
+                over: session.environment.is_done(),
+                legal: DIRECTIONS
+                    .iter()
+                    .zip(legal.iter())
+                    .filter(|(_, legal)| **legal)
+                    .map(|(direction, _)| direction_name(*direction))
+                    .collect(),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sessions;
+    use serde_json::Value;
+
+    fn json(body: &str) -> Value {
+        serde_json::from_str(body).unwrap()
+    }
+
+    /// Create a game and return its first legal direction
+    fn create(sessions: &mut Sessions) -> String {
+        let created = json(&sessions.handle("POST", "/games", r#"{"seed": 5}"#).body);
+        created["legal"][0].as_str().unwrap().to_string()
+    }
+
+    mod handle {
+        use super::*;
+
+        #[test]
+        fn creates_games_from_a_seed() {
+            let mut sessions = Sessions::new();
+            let first = json(&sessions.handle("POST", "/games", r#"{"seed": 9}"#).body);
+            let second = json(&sessions.handle("POST", "/games", r#"{"seed": 9}"#).body);
+
+            assert_eq!(first["id"], 1);
+            assert_eq!(second["id"], 2);
+            assert_eq!(first["board"], second["board"]);
+            assert_eq!(first["board"].as_array().unwrap().len(), 4);
+        }
+
+        #[test]
+        fn moves_and_undoes() {
+            let mut sessions = Sessions::new();
+            let direction = create(&mut sessions);
+            let before = sessions.handle("GET", "/games/1", "").body;
+
+            let moved = sessions.handle(
+                "POST",
+                "/games/1/moves",
+                &format!(r#"{{"direction": "{}"}}"#, direction),
+            );
+            assert_eq!(moved.status, 200);
+            assert_eq!(json(&moved.body)["moves"], 1);
+
+            let undone = sessions.handle("POST", "/games/1/undo", "");
+            assert_eq!(undone.body, before);
+            assert_eq!(sessions.handle("POST", "/games/1/undo", "").status, 409);
+        }
+
+        #[test]
+        fn rejects_invalid_requests() {
+            let mut sessions = Sessions::new();
+            create(&mut sessions);
+
+            assert_eq!(sessions.handle("POST", "/games", "{").status, 400);
+            assert_eq!(
+                sessions
+                    .handle("POST", "/games/1/moves", r#"{"direction": "north"}"#)
+                    .status,
+                400
+            );
+            assert_eq!(sessions.handle("GET", "/games/2", "").status, 404);
+            assert_eq!(sessions.handle("GET", "/games/x", "").status, 404);
+            assert_eq!(sessions.handle("PUT", "/games/1", "").status, 405);
+            assert_eq!(sessions.handle("GET", "/other", "").status, 404);
+        }
+
+        #[test]
+        fn deletes_games() {
+            let mut sessions = Sessions::new();
+            create(&mut sessions);
+
+            assert_eq!(sessions.handle("DELETE", "/games/1", "").status, 204);
+            assert!(sessions.is_empty());
+            assert_eq!(sessions.handle("GET", "/games/1", "").status, 404);
+        }
+
+        #[test]
+        fn forgets_the_least_recently_used_game() {
+            let mut sessions = Sessions::new().max_games(2);
+            create(&mut sessions);
+            create(&mut sessions);
+            sessions.handle("GET", "/games/1", "");
+            create(&mut sessions);
+
+            assert_eq!(sessions.len(), 2);
+            assert_eq!(sessions.handle("GET", "/games/1", "").status, 200);
+            assert_eq!(sessions.handle("GET", "/games/2", "").status, 404);
+            assert_eq!(sessions.handle("GET", "/games/3", "").status, 200);
+        }
+    }
+}
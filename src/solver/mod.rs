@@ -0,0 +1,20 @@
+//! Exact solutions of the game
+//!
+//! The solvers search every spawn and every move instead of estimating them,
+//! which makes them the ground truth that heuristics and learned agents can be
+//! measured against.
+//!
+//! ```
+//! use game_2048_model::solver::{Objective, Solver};
+//!
+//! let mut solver = Solver::new(2, Objective::Score);
+//! let expected_score = solver.solve_new_game();
+//!
+//! assert!(expected_score > 0.0);
+//! ```
+
+mod small;
+mod target;
+
+pub use small::{Objective, Solution, Solver};
+pub use target::{TargetProbability, TargetSearch};
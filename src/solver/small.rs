@@ -0,0 +1,595 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+use crate::base::*;
+
+const MAGIC: &[u8; 4] = b"SOLV";
+const VERSION: u32 = 1;
+
+/// What the [`Solver`] maximises
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Objective {
+    /// The expected score gained by merges until the game is over
+    Score,
+    /// The probability of building a number with at least this exponent
+    Reach(BoardElement),
+}
+
+/// The optimal value of a board and the direction reaching it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Solution {
+    /// The expected score or probability under optimal play
+    pub value: f64,
+    /// The optimal direction, `None` if the game is over or the target is reached
+    pub direction: Option<Directions>,
+}
+
+/// Exact solver for square boards smaller than the game board
+///
+/// Boards are given as slices of exponents in row order, like an
+/// [`ArrayBoard`] but with `size * size` squares. Every board solved is kept in
+/// the policy table, so boards reached by earlier solutions are answered right
+/// away and the whole table can be saved to disk.
+///
+/// The number of boards grows very quickly with the size. Every 2x2 board is
+/// solved in milliseconds, 3x3 boards are practical when reaching a small
+/// target, and 4x4 boards are out of reach.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::models::Directions;
+/// use game_2048_model::solver::{Objective, Solver};
+///
+/// let mut solver = Solver::new(2, Objective::Reach(3));
+/// let solution = solver.solve(&[
+///     2,2,
+///     0,0
+/// ]);
+///
+/// // Merging the two 4s makes an 8 right away
+/// assert_eq!(solution.value, 1.0);
+/// assert!(solution.direction.is_some());
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct Solver {
+    size: usize,
+    objective: Objective,
+    table: HashMap<u64, Solution>,
+}
+
+impl Solver {
+    /// Create a solver for boards of `size` by `size` squares
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is not between 2 and the size of the game board.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::solver::{Objective, Solver};
+    ///
+    /// let solver = Solver::new(3, Objective::Reach(6));
+    /// ```
+    ///
+    pub fn new(size: usize, objective: Objective) -> Self {
+        assert!(
+            (2..=BOARD_SIZE).contains(&size),
+            "The solver handles boards of 2 to {} squares a side",
+            BOARD_SIZE
+        );
+        Solver {
+            size,
+            objective,
+            table: HashMap::new(),
+        }
+    }
+
+    /// The number of squares on a side of the boards
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::solver::{Objective, Solver};
+    ///
+    /// assert_eq!(Solver::new(3, Objective::Score).size(), 3);
+    /// ```
+    ///
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// What the solver maximises
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::solver::{Objective, Solver};
+    ///
+    /// assert_eq!(Solver::new(3, Objective::Score).objective(), Objective::Score);
+    /// ```
+    ///
+    pub fn objective(&self) -> Objective {
+        self.objective
+    }
+
+    /// The number of boards in the policy table
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::solver::{Objective, Solver};
+    ///
+    /// let mut solver = Solver::new(2, Objective::Score);
+    /// assert_eq!(solver.len(), 0);
+    ///
+    /// solver.solve(&[1, 0, 0, 0]);
+    /// assert!(solver.len() > 0);
+    /// ```
+    ///
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Returns `true` if no board has been solved yet
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::solver::{Objective, Solver};
+    ///
+    /// assert!(Solver::new(2, Objective::Score).is_empty());
+    /// ```
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// The optimal value and direction of a board where it is the player's turn
+    ///
+    /// # Panics
+    ///
+    /// Panics if the board does not have `size * size` squares or holds an
+    /// exponent above 15.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::models::Directions;
+    /// use game_2048_model::solver::{Objective, Solver};
+    ///
+    /// let mut solver = Solver::new(2, Objective::Score);
+    /// let solution = solver.solve(&[
+    ///     1,2,
+    ///     2,1
+    /// ]);
+    ///
+    /// assert_eq!(solution.value, 0.0);
+    /// assert_eq!(solution.direction, None);
+    /// ```
+    ///
+    pub fn solve(&mut self, board: &[BoardElement]) -> Solution {
+        assert_eq!(
+            board.len(),
+            self.size * self.size,
+            "Expected a board of size * size squares"
+        );
+        self.solve_key(self.pack(board))
+    }
+
+    /// The expected value of a new game, starting with two spawned numbers
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::solver::{Objective, Solver};
+    ///
+    /// // Every new game starts with at least a 2
+    /// let mut solver = Solver::new(2, Objective::Reach(1));
+    ///
+    /// assert!((solver.solve_new_game() - 1.0).abs() < 1e-12);
+    /// ```
+    ///
+    pub fn solve_new_game(&mut self) -> f64 {
+        let mut total = 0.0;
+        for (first, first_probability) in self.spawns(0) {
+            for (second, second_probability) in self.spawns(first) {
+                total += first_probability * second_probability * self.solve_key(second).value;
+            }
+        }
+        total
+    }
+
+    /// The solution of a board, if it is in the policy table
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::solver::{Objective, Solver};
+    ///
+    /// let mut solver = Solver::new(2, Objective::Score);
+    /// assert_eq!(solver.get(&[1, 0, 0, 0]), None);
+    ///
+    /// let solution = solver.solve(&[1, 0, 0, 0]);
+    /// assert_eq!(solver.get(&[1, 0, 0, 0]), Some(solution));
+    /// ```
+    ///
+    pub fn get(&self, board: &[BoardElement]) -> Option<Solution> {
+        if board.len() != self.size * self.size || board.iter().any(|x| *x > 15) {
+            return None;
+        }
+        self.table.get(&self.pack(board)).copied()
+    }
+
+    /// Every board in the policy table together with its solution
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::solver::{Objective, Solver};
+    ///
+    /// let mut solver = Solver::new(2, Objective::Reach(4));
+    /// solver.solve_new_game();
+    ///
+    /// for (board, solution) in solver.policy() {
+    ///     assert_eq!(board.len(), 4);
+    ///     assert!(solution.value <= 1.0);
+    /// }
+    /// ```
+    ///
+    pub fn policy(&self) -> impl Iterator<Item = (Vec<BoardElement>, Solution)> + '_ {
+        self.table
+            .iter()
+            .map(move |(key, solution)| (self.unpack(*key), *solution))
+    }
+
+    /// Write the policy table
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::solver::{Objective, Solver};
+    ///
+    /// let mut solver = Solver::new(2, Objective::Score);
+    /// solver.solve_new_game();
+    ///
+    /// let mut buffer = Vec::new();
+    /// solver.save(&mut buffer).unwrap();
+    /// let loaded = Solver::load(&mut buffer.as_slice()).unwrap();
+    ///
+    /// assert_eq!(loaded.get(&[1, 1, 0, 0]), solver.get(&[1, 1, 0, 0]));
+    /// ```
+    ///
+    pub fn save<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.size as u32).to_le_bytes())?;
+        match self.objective {
+            Objective::Score => writer.write_all(&[0, 0])?,
+            Objective::Reach(exponent) => writer.write_all(&[1, exponent])?,
+        }
+        writer.write_all(&(self.table.len() as u64).to_le_bytes())?;
+
+        // Sorted so the same table is always written the same way
+        let mut keys: Vec<&u64> = self.table.keys().collect();
+        keys.sort();
+        for key in keys {
+            let solution = self.table[key];
+            writer.write_all(&key.to_le_bytes())?;
+            writer.write_all(&solution.value.to_le_bytes())?;
+            let direction = match solution.direction {
+                Some(direction) => DIRECTIONS
+                    .iter()
+                    .position(|other| *other == direction)
+                    .expect("Every direction is in DIRECTIONS")
+                    as u8,
+                None => u8::MAX,
+            };
+            writer.write_all(&[direction])?;
+        }
+        Ok(())
+    }
+
+    /// Read a policy table written by [`Solver::save`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::solver::Solver;
+    ///
+    /// assert!(Solver::load(&mut "not a table".as_bytes()).is_err());
+    /// ```
+    ///
+    pub fn load<R: Read>(reader: &mut R) -> io::Result<Self> {
+        fn read<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+            let mut bytes = [0; N];
+            reader.read_exact(&mut bytes)?;
+            Ok(bytes)
+        }
+        fn invalid(message: &str) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        }
+
+        if &read::<R, 4>(reader)? != MAGIC {
+            return Err(invalid("Not a solver policy table"));
+        }
+        if u32::from_le_bytes(read(reader)?) != VERSION {
+            return Err(invalid("Unsupported solver policy table version"));
+        }
+        let size = u32::from_le_bytes(read(reader)?) as usize;
+        if !(2..=BOARD_SIZE).contains(&size) {
+            return Err(invalid("Unsupported board size"));
+        }
+        let objective = match read::<R, 2>(reader)? {
+            [0, _] => Objective::Score,
+            [1, exponent] => Objective::Reach(exponent),
+            _ => return Err(invalid("Unknown objective")),
+        };
+
+        let mut solver = Solver::new(size, objective);
+        let count = u64::from_le_bytes(read(reader)?);
+        for _ in 0..count {
+            let key = u64::from_le_bytes(read(reader)?);
+            if size < BOARD_SIZE && key >> (4 * size * size) != 0 {
+                return Err(invalid("Board outside the table size"));
+            }
+            let value = f64::from_le_bytes(read(reader)?);
+            let direction = match read::<R, 1>(reader)?[0] {
+                u8::MAX => None,
+                index => Some(
+                    *DIRECTIONS
+                        .get(usize::from(index))
+                        .ok_or_else(|| invalid("Unknown direction"))?,
+                ),
+            };
+            solver.table.insert(key, Solution { value, direction });
+        }
+        Ok(solver)
+    }
+
+    fn pack(&self, board: &[BoardElement]) -> u64 {
+        board.iter().enumerate().fold(0, |key, (square, exponent)| {
+            assert!(*exponent <= 15, "The solver handles exponents up to 15");
+            key | u64::from(*exponent) << (4 * square)
+        })
+    }
+
+    fn unpack(&self, key: u64) -> Vec<BoardElement> {
+        (0..self.size * self.size)
+            .map(|square| ((key >> (4 * square)) & 0xf) as BoardElement)
+            .collect()
+    }
+
+    fn exponent(key: u64, square: usize) -> BoardElement {
+        ((key >> (4 * square)) & 0xf) as BoardElement
+    }
+
+    /// Every board a spawn can make from the board, with its probability
+    fn spawns(&self, key: u64) -> Vec<(u64, f64)> {
+        let squares = self.size * self.size;
+        let empty: Vec<usize> = (0..squares)
+            .filter(|square| Solver::exponent(key, *square) == 0)
+            .collect();
+        let mut children = Vec::with_capacity(2 * empty.len());
+        for square in empty.iter() {
+            for (exponent, probability) in SPAWN_PROBABILITIES.iter() {
+                children.push((
+                    key | u64::from(*exponent) << (4 * square),
+                    probability / empty.len() as f64,
+                ));
+            }
+        }
+        children
+    }
+
+    /// Slide the board, returning the new board and the score gained, or `None`
+    /// if the board does not change
+    fn slide(&self, key: u64, direction: Directions) -> Option<(u64, u64)> {
+        let size = self.size;
+        let mut result = key;
+        let mut score = 0;
+        for line in 0..size {
+            // The squares of the line, starting at the side the numbers slide towards
+            let mut squares = [0; BOARD_SIZE];
+            for (step, square) in squares.iter_mut().take(size).enumerate() {
+                *square = match direction {
+                    Directions::Up => step * size + line,
+                    Directions::Down => (size - 1 - step) * size + line,
+                    Directions::Left => line * size + step,
+                    Directions::Right => line * size + size - 1 - step,
+                };
+            }
+            let squares = &squares[..size];
+
+            let mut merged = [0; BOARD_SIZE];
+            for (exponent, square) in merged.iter_mut().zip(squares.iter()) {
+                *exponent = Solver::exponent(key, *square);
+            }
+            merge_line(&mut merged[..size], |exponent| {
+                assert!(exponent <= 15, "The solver handles exponents up to 15");
+                score += 1u64 << exponent;
+            });
+
+            for (square, exponent) in squares.iter().zip(merged.iter()) {
+                result &= !(0xf << (4 * square));
+                result |= u64::from(*exponent) << (4 * square);
+            }
+        }
+
+        if result == key {
+            None
+        } else {
+            Some((result, score))
+        }
+    }
+
+    fn solve_key(&mut self, key: u64) -> Solution {
+        if let Some(solution) = self.table.get(&key) {
+            return *solution;
+        }
+
+        let reached = match self.objective {
+            Objective::Score => false,
+            Objective::Reach(target) => {
+                (0..self.size * self.size).any(|square| Solver::exponent(key, square) >= target)
+            }
+        };
+        let solution = if reached {
+            Solution {
+                value: 1.0,
+                direction: None,
+            }
+        } else {
+            let mut best = Solution {
+                value: 0.0,
+                direction: None,
+            };
+            for direction in DIRECTIONS.iter() {
+                if let Some((after, score)) = self.slide(key, *direction) {
+                    let mut value = match self.objective {
+                        Objective::Score => score as f64,
+                        Objective::Reach(_) => 0.0,
+                    };
+                    for (child, probability) in self.spawns(after) {
+                        value += probability * self.solve_key(child).value;
+                    }
+                    if best.direction.is_none() || value > best.value {
+                        best = Solution {
+                            value,
+                            direction: Some(*direction),
+                        };
+                    }
+                }
+            }
+            best
+        };
+
+        self.table.insert(key, solution);
+        solution
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Objective, Solver};
+    use crate::models::Directions;
+
+    mod slide {
+        use super::*;
+
+        #[test]
+        fn slides_like_the_game() {
+            let solver = Solver::new(3, Objective::Score);
+            let key = solver.pack(&[1, 1, 1, 0, 2, 2, 3, 0, 3]);
+
+            let (after, score) = solver.slide(key, Directions::Left).unwrap();
+            assert_eq!(solver.unpack(after), vec![2, 1, 0, 3, 0, 0, 4, 0, 0]);
+            assert_eq!(score, 4 + 8 + 16);
+
+            let (after, _) = solver.slide(key, Directions::Down).unwrap();
+            assert_eq!(solver.unpack(after), vec![0, 0, 1, 1, 1, 2, 3, 2, 3]);
+        }
+
+        #[test]
+        fn unchanged_board_is_none() {
+            let solver = Solver::new(2, Objective::Score);
+            let key = solver.pack(&[1, 0, 2, 0]);
+
+            assert_eq!(solver.slide(key, Directions::Left), None);
+            assert_eq!(solver.slide(key, Directions::Up), None);
+        }
+    }
+
+    mod solve {
+        use super::*;
+
+        #[test]
+        fn averages_over_spawns() {
+            // Only the merge of the 2s is possible, after which the spawn
+            // fills the last empty square.
+            let mut solver = Solver::new(2, Objective::Score);
+            let solution = solver.solve(&[1, 1, 3, 2]);
+
+            let left = 4.0
+                + 0.9 * solver.solve(&[2, 1, 3, 2]).value
+                + 0.1 * solver.solve(&[2, 2, 3, 2]).value;
+            let right = 4.0
+                + 0.9 * solver.solve(&[1, 2, 3, 2]).value
+                + 0.1 * solver.solve(&[2, 2, 3, 2]).value;
+            assert_eq!(solution.value, left.max(right));
+            if left >= right {
+                assert_eq!(solution.direction, Some(Directions::Left));
+            } else {
+                assert_eq!(solution.direction, Some(Directions::Right));
+            }
+        }
+
+        #[test]
+        fn reach_is_a_probability() {
+            let mut solver = Solver::new(2, Objective::Reach(5));
+            let value = solver.solve_new_game();
+
+            assert!(value > 0.0 && value < 1.0);
+            for (_, solution) in solver.policy() {
+                assert!((0.0..=1.0).contains(&solution.value));
+            }
+        }
+
+        #[test]
+        fn unreachable_target_on_a_small_board() {
+            // A 2x2 board can hold at most 4 numbers, so the largest number
+            // stays far below 2^10.
+            let mut solver = Solver::new(2, Objective::Reach(10));
+
+            assert_eq!(solver.solve_new_game(), 0.0);
+        }
+
+        #[test]
+        fn three_by_three_reaches_small_targets() {
+            let mut solver = Solver::new(3, Objective::Reach(4));
+
+            assert!(solver.solve_new_game() > 0.99);
+        }
+
+        #[test]
+        #[should_panic]
+        fn panics_on_wrong_size() {
+            Solver::new(2, Objective::Score).solve(&[0; 9]);
+        }
+    }
+
+    mod load {
+        use super::*;
+
+        #[test]
+        fn round_trip_keeps_every_solution() {
+            let mut solver = Solver::new(2, Objective::Reach(4));
+            solver.solve_new_game();
+            let mut buffer = Vec::new();
+            solver.save(&mut buffer).unwrap();
+            let loaded = Solver::load(&mut buffer.as_slice()).unwrap();
+
+            assert_eq!(loaded.objective(), Objective::Reach(4));
+            assert_eq!(loaded.len(), solver.len());
+            for (board, solution) in solver.policy() {
+                assert_eq!(loaded.get(&board), Some(solution));
+            }
+        }
+
+        #[test]
+        fn rejects_truncated_tables() {
+            let mut solver = Solver::new(2, Objective::Score);
+            solver.solve(&[1, 1, 0, 0]);
+            let mut buffer = Vec::new();
+            solver.save(&mut buffer).unwrap();
+            buffer.pop();
+
+            assert!(Solver::load(&mut buffer.as_slice()).is_err());
+        }
+    }
+}
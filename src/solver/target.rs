@@ -0,0 +1,326 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use std::collections::HashMap;
+
+use crate::ai::best_of;
+use crate::base::*;
+use crate::encoding::MAX_EXPONENT;
+
+/// The chance of reaching a target number found by [`TargetSearch`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TargetProbability {
+    /// The probability of reaching the target under optimal play
+    pub probability: f64,
+    /// The optimal first direction, `None` if the target is already on the
+    /// board or no direction changes it
+    pub direction: Option<Directions>,
+    /// The probability after each direction in the order of [`DIRECTIONS`],
+    /// `None` for directions that do not change the board
+    pub values: [Option<f64>; 4],
+}
+
+/// Exact probability of building a number within a number of moves
+///
+/// Every move and every spawn is searched, with the spawn probabilities used
+/// by [`Model::random`], so the probability is exact for the given number of
+/// moves and a lower bound for the game without a move limit. Boards that
+/// cannot hold enough in total to make the target in the moves left are not
+/// searched, since a spawn adds at most a 4 to the board.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::models::{ArrayModel, Directions};
+/// use game_2048_model::solver::TargetSearch;
+///
+/// // 2048, 1024, 512 and 512 on the bottom row
+/// let game = ArrayModel::from([
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     11,10,9,9
+/// ]);
+///
+/// // Merging right builds 4096 in three moves no matter the spawns
+/// let result = TargetSearch::new(12).moves(3).search(&game);
+/// assert_eq!(result.probability, 1.0);
+/// assert_eq!(result.direction, Some(Directions::Right));
+/// ```
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TargetSearch {
+    target: BoardElement,
+    moves: usize,
+}
+
+impl TargetSearch {
+    /// Create a search for the number 2^`target` looking 4 moves ahead
+    ///
+    /// # Panics
+    ///
+    /// Panics if the target is not between 1 and 17, the largest exponent that
+    /// can be reached on the board.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::solver::TargetSearch;
+    ///
+    /// // Can 2048 still be made?
+    /// let search = TargetSearch::new(11);
+    /// ```
+    ///
+    pub fn new(target: BoardElement) -> Self {
+        assert!(
+            (1..=MAX_EXPONENT).contains(&target),
+            "The target exponent must be between 1 and {}",
+            MAX_EXPONENT
+        );
+        TargetSearch { target, moves: 4 }
+    }
+
+    /// Set the number of moves the target has to be reached in
+    ///
+    /// The search grows quickly with the number of moves, unless the board is
+    /// close to the target.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::solver::TargetSearch;
+    ///
+    /// let search = TargetSearch::new(12).moves(6);
+    /// ```
+    ///
+    pub fn moves(mut self, moves: usize) -> Self {
+        self.moves = moves;
+        self
+    }
+
+    /// The probability of reaching the target from the board with optimal play
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::models::ArrayModel;
+    /// use game_2048_model::solver::TargetSearch;
+    ///
+    /// let game = ArrayModel::from([
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,0,
+    ///     0,0,0,1
+    /// ]);
+    ///
+    /// // A 2 and the spawned number hold at most 6, too little for an 8
+    /// assert_eq!(TargetSearch::new(3).moves(1).search(&game).probability, 0.0);
+    /// ```
+    ///
+    pub fn search<M: Model>(&self, game: &M) -> TargetProbability {
+        let board = game.as_array();
+        let mut values = [None; 4];
+        if self.reached(&board) {
+            return TargetProbability {
+                probability: 1.0,
+                direction: None,
+                values,
+            };
+        }
+
+        let mut memory = HashMap::new();
+        if self.moves > 0 {
+            for (value, direction) in values.iter_mut().zip(DIRECTIONS.iter()) {
+                let mut child = M::from(board);
+                if child.slide(*direction).is_some() {
+                    *value =
+                        Some(self.chance_node::<M>(&mut memory, child.as_array(), self.moves - 1));
+                }
+            }
+        }
+
+        let direction = best_of(&values);
+        TargetProbability {
+            // DIRECTIONS lists the directions in declaration order
+            probability: direction
+                .and_then(|direction| values[direction as usize])
+                .unwrap_or(0.0),
+            direction,
+            values,
+        }
+    }
+
+    fn reached(&self, board: &ArrayBoard) -> bool {
+        board.iter().any(|x| *x >= self.target)
+    }
+
+    /// Whether the numbers on the board together with the largest possible
+    /// spawns can add up to the target
+    fn reachable(&self, board: &ArrayBoard, moves: usize) -> bool {
+        let total: u64 = board.iter().filter(|x| **x > 0).map(|x| 1u64 << *x).sum();
+        total + 4 * (moves as u64 + 1) >= 1u64 << self.target
+    }
+
+    /// Probability of reaching the target when it is the player's turn with `moves` left
+    fn max_node<M: Model>(
+        &self,
+        memory: &mut HashMap<(ArrayBoard, usize), f64>,
+        board: ArrayBoard,
+        moves: usize,
+    ) -> f64 {
+        if self.reached(&board) {
+            return 1.0;
+        }
+        if moves == 0 || !self.reachable(&board, moves - 1) {
+            return 0.0;
+        }
+        if let Some(value) = memory.get(&(board, moves)) {
+            return *value;
+        }
+
+        let mut best = 0.0;
+        for direction in DIRECTIONS.iter() {
+            let mut child = M::from(board);
+            if child.slide(*direction).is_some() {
+                let value = self.chance_node::<M>(memory, child.as_array(), moves - 1);
+                if value > best {
+                    best = value;
+                }
+            }
+        }
+        memory.insert((board, moves), best);
+        best
+    }
+
+    /// Probability of reaching the target over every spawn after a move
+    fn chance_node<M: Model>(
+        &self,
+        memory: &mut HashMap<(ArrayBoard, usize), f64>,
+        board: ArrayBoard,
+        moves: usize,
+    ) -> f64 {
+        if self.reached(&board) {
+            return 1.0;
+        }
+        let empty = board.iter().filter(|x| **x == 0).count();
+        let mut total = 0.0;
+        for square in 0..board.len() {
+            if board[square] != 0 {
+                continue;
+            }
+            for (exponent, spawn_probability) in SPAWN_PROBABILITIES.iter() {
+                let mut child = board;
+                child[square] = *exponent;
+                total += spawn_probability * self.max_node::<M>(memory, child, moves);
+            }
+        }
+        total / empty as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TargetSearch;
+    use crate::models::{ArrayModel, Directions};
+
+    mod new {
+        use super::*;
+
+        #[test]
+        fn accepts_every_exponent_on_the_board() {
+            TargetSearch::new(1);
+            let result = TargetSearch::new(17).search(&ArrayModel::from([1; 16]));
+
+            assert_eq!(result.probability, 0.0);
+        }
+
+        #[test]
+        #[should_panic(expected = "between 1 and 17")]
+        fn rejects_exponents_above_the_board() {
+            TargetSearch::new(18);
+        }
+
+        #[test]
+        #[should_panic(expected = "between 1 and 17")]
+        fn rejects_zero() {
+            TargetSearch::new(0);
+        }
+    }
+
+    mod search {
+        use super::*;
+
+        #[test]
+        fn target_on_the_board_is_certain() {
+            let mut board = [0; 16];
+            board[5] = 11;
+            let result = TargetSearch::new(11).search(&ArrayModel::from(board));
+
+            assert_eq!(result.probability, 1.0);
+            assert_eq!(result.direction, None);
+        }
+
+        #[test]
+        fn no_moves_left_is_impossible() {
+            let board = [1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+            let result = TargetSearch::new(2)
+                .moves(0)
+                .search(&ArrayModel::from(board));
+
+            assert_eq!(result.probability, 0.0);
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn spawns_can_reach_small_targets() {
+            // Either a 4 is spawned or the two 2s merge
+            let board = [
+                1,0,0,0,
+                0,0,0,0,
+                0,0,0,0,
+                0,0,0,0
+            ];
+            let result = TargetSearch::new(2).moves(1).search(&ArrayModel::from(board));
+
+            assert!((result.probability - 0.1).abs() < 1e-12);
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn prefers_the_direction_keeping_the_merge() {
+            // Sliding up or down leaves the 4s next to each other, sliding
+            // left or right merges them into the 8 right away.
+            let board = [
+                0,0,0,0,
+                0,0,0,0,
+                0,0,0,0,
+                2,2,0,0
+            ];
+            let result = TargetSearch::new(3).moves(1).search(&ArrayModel::from(board));
+
+            assert_eq!(result.probability, 1.0);
+            assert_eq!(result.values[0], Some(0.0));
+            assert_eq!(result.values[1], Some(1.0));
+            assert_eq!(result.direction, Some(Directions::Right));
+        }
+
+        #[rustfmt::skip]
+        #[test]
+        fn more_moves_never_lower_the_probability() {
+            let board = [
+                0,0,0,0,
+                0,1,0,0,
+                0,0,2,0,
+                1,0,2,3
+            ];
+            let game = ArrayModel::from(board);
+            let mut previous = 0.0;
+
+            for moves in 1..=4 {
+                let probability = TargetSearch::new(5).moves(moves).search(&game).probability;
+                assert!(probability >= previous);
+                previous = probability;
+            }
+        }
+    }
+}
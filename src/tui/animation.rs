@@ -0,0 +1,131 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use crate::base::*;
+
+/// Where a number on the board goes during a slide
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TileMove {
+    /// The square the number starts on, indexed like an [`ArrayBoard`]
+    pub from: usize,
+    /// The square the number ends on
+    pub to: usize,
+    /// The exponent of the number before the slide
+    pub exponent: BoardElement,
+    /// Whether the number merges with another number on `to`
+    pub merged: bool,
+}
+
+/// Every number on the board and where it slides to
+///
+/// Numbers that do not move are included with `from` equal to `to`, so the
+/// moves together describe the whole board during the slide. Both numbers of
+/// a merge are marked as merged.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::models::Directions;
+/// use game_2048_model::tui::{tile_moves, TileMove};
+///
+/// let board = [
+///     1,1,0,0,
+///     0,0,0,0,
+///     0,0,0,0,
+///     0,0,0,0
+/// ];
+/// let moves = tile_moves(&board, Directions::Right);
+///
+/// assert_eq!(moves, vec![
+///     TileMove { from: 1, to: 3, exponent: 1, merged: true },
+///     TileMove { from: 0, to: 3, exponent: 1, merged: true },
+/// ]);
+/// ```
+///
+pub fn tile_moves(board: &ArrayBoard, direction: Directions) -> Vec<TileMove> {
+    let mut moves: Vec<TileMove> = Vec::new();
+    for line in 0..BOARD_SIZE {
+        // The squares of the line starting at the edge the numbers slide towards
+        let mut squares = [0; BOARD_SIZE];
+        for (i, square) in squares.iter_mut().enumerate() {
+            *square = match direction {
+                Directions::Up => i * BOARD_SIZE + line,
+                Directions::Right => line * BOARD_SIZE + BOARD_SIZE - 1 - i,
+                Directions::Down => (BOARD_SIZE - 1 - i) * BOARD_SIZE + line,
+                Directions::Left => line * BOARD_SIZE + i,
+            };
+        }
+
+        let mut target = 0;
+        // The last number placed on the line, if it can still merge
+        let mut last: Option<usize> = None;
+        for square in squares.iter() {
+            let exponent = board[*square];
+            if exponent == 0 {
+                continue;
+            }
+            match last {
+                Some(index) if moves[index].exponent == exponent => {
+                    moves[index].merged = true;
+                    moves.push(TileMove {
+                        from: *square,
+                        to: moves[index].to,
+                        exponent,
+                        merged: true,
+                    });
+                    last = None;
+                }
+                _ => {
+                    moves.push(TileMove {
+                        from: *square,
+                        to: squares[target],
+                        exponent,
+                        merged: false,
+                    });
+                    last = Some(moves.len() - 1);
+                    target += 1;
+                }
+            }
+        }
+    }
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tile_moves;
+    use crate::models::{ArrayModel, Model, DIRECTIONS};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    mod tile_moves {
+        use super::*;
+
+        #[test]
+        fn moves_end_on_the_slid_board() {
+            let mut rng = StdRng::seed_from_u64(12);
+            let mut game = ArrayModel::new();
+            for turn in 0..300 {
+                if game.random(&mut rng).is_err() {
+                    break;
+                }
+                let board = game.as_array();
+                for direction in DIRECTIONS.iter() {
+                    let mut expected = ArrayModel::from(board);
+                    expected.slide(*direction);
+
+                    let mut slid = [0; 16];
+                    for tile in tile_moves(&board, *direction) {
+                        slid[tile.to] = if tile.merged {
+                            tile.exponent + 1
+                        } else {
+                            tile.exponent
+                        };
+                    }
+                    assert_eq!(slid, expected.as_array());
+                }
+                game.slide(DIRECTIONS[turn % 4]);
+            }
+        }
+    }
+}
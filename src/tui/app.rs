@@ -0,0 +1,512 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use std::fmt;
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Widget};
+
+use super::animation::{tile_moves, TileMove};
+use super::colors::{tile_colors, BOARD};
+use crate::ai::heuristics::Weighted;
+use crate::ai::{hint_with, Expectimax, Hint};
+use crate::base::*;
+use crate::environment::Environment;
+use crate::models::ArrayModel;
+
+const TILE_WIDTH: u16 = 7;
+const TILE_HEIGHT: u16 = 3;
+const BOARD_WIDTH: u16 = 4 * TILE_WIDTH + 5;
+const BOARD_HEIGHT: u16 = 4 * TILE_HEIGHT + 5;
+const PANEL_WIDTH: u16 = 24;
+
+/// The smallest area the app can be drawn in, as width and height
+pub const MIN_SIZE: (u16, u16) = (BOARD_WIDTH + 1 + PANEL_WIDTH, BOARD_HEIGHT + 1);
+
+/// Frames spent sliding the numbers to their squares
+const SLIDE_FRAMES: usize = 6;
+/// Frames the merged numbers are drawn enlarged after the slide
+const POP_FRAMES: usize = 3;
+
+/// The state of a game shown in the terminal
+///
+/// The app draws itself as a [`Widget`], so it can be rendered into any
+/// [`Buffer`], and is driven by calling [`App::slide`] on key presses and
+/// [`App::tick`] once per frame while [`App::is_animating`].
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::models::Directions;
+/// use game_2048_model::tui::App;
+/// use ratatui::buffer::Buffer;
+/// use ratatui::layout::Rect;
+/// use ratatui::widgets::Widget;
+///
+/// let mut app = App::new(42);
+/// app.slide(Directions::Left);
+/// while app.tick() {}
+///
+/// // Render without a terminal
+/// let area = Rect::new(0, 0, 60, 20);
+/// let mut buffer = Buffer::empty(area);
+/// app.render(area, &mut buffer);
+/// ```
+///
+pub struct App {
+    environment: Environment<ArrayModel>,
+    seed: u64,
+    history: Vec<Directions>,
+    animation: Option<(Vec<TileMove>, usize)>,
+    search: Expectimax<Weighted>,
+    hint: Option<Hint>,
+}
+
+impl fmt::Debug for App {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("App")
+            .field("board", &self.environment.observation())
+            .field("seed", &self.seed)
+            .field("history", &self.history)
+            .finish_non_exhaustive()
+    }
+}
+
+impl App {
+    /// Start a new game from a seed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::tui::App;
+    ///
+    /// let app = App::new(7);
+    /// assert_eq!(app.seed(), 7);
+    /// ```
+    ///
+    pub fn new(seed: u64) -> Self {
+        let mut app = App {
+            environment: Environment::new(),
+            seed,
+            history: Vec::new(),
+            animation: None,
+            search: Expectimax::default().depth(2),
+            hint: None,
+        };
+        app.restart(seed);
+        app
+    }
+
+    /// Start a new game from a seed, forgetting the current one
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::models::Directions;
+    /// use game_2048_model::tui::App;
+    ///
+    /// let mut app = App::new(7);
+    /// app.slide(Directions::Up);
+    /// app.restart(8);
+    ///
+    /// assert!(app.history().is_empty());
+    /// ```
+    ///
+    pub fn restart(&mut self, seed: u64) {
+        self.environment.reset(seed);
+        self.seed = seed;
+        self.history.clear();
+        self.animation = None;
+        self.update_hint();
+    }
+
+    /// Slide towards the direction and start animating it
+    ///
+    /// Returns false if the direction does not change the board. A slide
+    /// during an animation skips the rest of the animation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::models::Directions;
+    /// use game_2048_model::tui::App;
+    ///
+    /// let mut app = App::new(7);
+    ///
+    /// if app.slide(Directions::Up) {
+    ///     assert!(app.is_animating());
+    /// }
+    /// ```
+    ///
+    pub fn slide(&mut self, direction: Directions) -> bool {
+        let before = self.environment.observation();
+        if self.environment.step(direction).info.invalid_move {
+            return false;
+        }
+        self.animation = Some((tile_moves(&before, direction), 0));
+        self.history.push(direction);
+        self.update_hint();
+        true
+    }
+
+    /// Slide towards the hinted direction
+    ///
+    /// Returns false if the game is over.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::tui::App;
+    ///
+    /// let mut app = App::new(7);
+    /// assert!(app.play_hint());
+    /// ```
+    ///
+    pub fn play_hint(&mut self) -> bool {
+        match self.hint.as_ref().map(|hint| hint.direction) {
+            Some(direction) => self.slide(direction),
+            None => false,
+        }
+    }
+
+    /// Advance the animation by one frame, returns whether it is still running
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::models::Directions;
+    /// use game_2048_model::tui::App;
+    ///
+    /// let mut app = App::new(7);
+    /// app.slide(Directions::Down);
+    /// while app.tick() {}
+    ///
+    /// assert!(!app.is_animating());
+    /// ```
+    ///
+    pub fn tick(&mut self) -> bool {
+        if let Some((_, frame)) = &mut self.animation {
+            *frame += 1;
+            if *frame >= SLIDE_FRAMES + POP_FRAMES {
+                self.animation = None;
+            }
+        }
+        self.is_animating()
+    }
+
+    /// Whether a slide is being animated
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::tui::App;
+    ///
+    /// assert!(!App::new(7).is_animating());
+    /// ```
+    ///
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// The game being played
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::tui::App;
+    ///
+    /// assert_eq!(App::new(7).game().score(), 0);
+    /// ```
+    ///
+    pub fn game(&self) -> &Environment<ArrayModel> {
+        &self.environment
+    }
+
+    /// The seed of the game
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::tui::App;
+    ///
+    /// assert_eq!(App::new(3).seed(), 3);
+    /// ```
+    ///
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Every direction played so far
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::tui::App;
+    ///
+    /// assert!(App::new(3).history().is_empty());
+    /// ```
+    ///
+    pub fn history(&self) -> &[Directions] {
+        &self.history
+    }
+
+    /// The best direction found by a two move lookahead, `None` when the game is over
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::tui::App;
+    ///
+    /// assert!(App::new(3).hint().is_some());
+    /// ```
+    ///
+    pub fn hint(&self) -> Option<&Hint> {
+        self.hint.as_ref()
+    }
+
+    fn update_hint(&mut self) {
+        self.hint = hint_with(self.environment.game(), &self.search)
+            .into_iter()
+            .next()
+            .filter(|hint| hint.legal);
+    }
+
+    fn render_board(&self, area: Rect, buf: &mut Buffer) {
+        buf.set_style(area, Style::default().bg(BOARD));
+        for square in 0..BOARD_SIZE * BOARD_SIZE {
+            let (x, y) = tile_position(square);
+            draw_tile(buf, area, x, y, 0, false);
+        }
+
+        match &self.animation {
+            Some((moves, frame)) if *frame < SLIDE_FRAMES => {
+                let progress = *frame as f64 / SLIDE_FRAMES as f64;
+                // Moving numbers are drawn last so they slide over the others
+                let (still, moving): (Vec<&TileMove>, Vec<&TileMove>) =
+                    moves.iter().partition(|tile| tile.from == tile.to);
+                for tile in still.into_iter().chain(moving) {
+                    let (from_x, from_y) = tile_position(tile.from);
+                    let (to_x, to_y) = tile_position(tile.to);
+                    let x = f64::from(from_x) + (f64::from(to_x) - f64::from(from_x)) * progress;
+                    let y = f64::from(from_y) + (f64::from(to_y) - f64::from(from_y)) * progress;
+                    draw_tile(
+                        buf,
+                        area,
+                        x.round() as u16,
+                        y.round() as u16,
+                        tile.exponent,
+                        false,
+                    );
+                }
+            }
+            animation => {
+                let merged: Vec<usize> = animation
+                    .iter()
+                    .flat_map(|(moves, _)| moves.iter())
+                    .filter(|tile| tile.merged)
+                    .map(|tile| tile.to)
+                    .collect();
+                for (square, exponent) in self.environment.observation().iter().enumerate() {
+                    if *exponent != 0 {
+                        let (x, y) = tile_position(square);
+                        draw_tile(buf, area, x, y, *exponent, merged.contains(&square));
+                    }
+                }
+            }
+        }
+    }
+
+    fn render_panel(&self, area: Rect, buf: &mut Buffer) {
+        let board = self.environment.observation();
+        let best = board.iter().max().copied().unwrap_or(0);
+        let mut lines = vec![
+            Line::from(format!("Score  {}", self.environment.score())),
+            Line::from(format!("Best   {}", 1u64 << best)),
+            Line::from(format!("Moves  {}", self.history.len())),
+            Line::from(format!("Seed   {}", self.seed)),
+            Line::from(""),
+        ];
+        match &self.hint {
+            Some(hint) => {
+                lines.push(Line::from(format!(
+                    "Hint   {} {:?}",
+                    arrow(hint.direction),
+                    hint.direction
+                )));
+                if let Some(reason) = hint.reasons().first() {
+                    lines.push(Line::from(format!("       {}", reason.name)));
+                }
+            }
+            None => lines
+                .push(Line::from("Game over").style(Style::default().add_modifier(Modifier::BOLD))),
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from("History").style(Style::default().add_modifier(Modifier::BOLD)));
+
+        // Borders take two rows, the newest moves are shown last
+        let rows = usize::from(area.height.saturating_sub(2)).saturating_sub(lines.len());
+        let skip = self.history.len().saturating_sub(rows);
+        for (turn, direction) in self.history.iter().enumerate().skip(skip) {
+            lines.push(Line::from(format!(
+                "{:>5}  {} {:?}",
+                turn + 1,
+                arrow(*direction),
+                direction
+            )));
+        }
+
+        Paragraph::new(lines)
+            .block(Block::bordered().title(" 2048 "))
+            .render(area, buf);
+    }
+}
+
+impl Widget for &App {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width < MIN_SIZE.0 || area.height < MIN_SIZE.1 {
+            Paragraph::new(format!(
+                "Make the terminal at least {}x{}",
+                MIN_SIZE.0, MIN_SIZE.1
+            ))
+            .render(area, buf);
+            return;
+        }
+
+        let board = Rect::new(area.x, area.y, BOARD_WIDTH, BOARD_HEIGHT);
+        let panel = Rect::new(
+            area.x + BOARD_WIDTH + 1,
+            area.y,
+            area.width - BOARD_WIDTH - 1,
+            BOARD_HEIGHT,
+        );
+        let footer = Rect::new(area.x, area.y + BOARD_HEIGHT, area.width, 1);
+
+        self.render_board(board, buf);
+        self.render_panel(panel, buf);
+        Paragraph::new("←↑→↓ or WASD slide  space play hint  n new game  q quit")
+            .style(Style::default().add_modifier(Modifier::DIM))
+            .render(footer, buf);
+    }
+}
+
+/// The top left corner of the square on the board
+fn tile_position(square: usize) -> (u16, u16) {
+    let column = (square % BOARD_SIZE) as u16;
+    let row = (square / BOARD_SIZE) as u16;
+    (1 + column * (TILE_WIDTH + 1), 1 + row * (TILE_HEIGHT + 1))
+}
+
+/// Draw a number with its top left corner at the position on the board,
+/// enlarged into the gaps around it if it pops
+fn draw_tile(buf: &mut Buffer, board: Rect, x: u16, y: u16, exponent: BoardElement, pop: bool) {
+    let (background, text) = tile_colors(exponent);
+    let mut tile = Rect::new(board.x + x, board.y + y, TILE_WIDTH, TILE_HEIGHT);
+    if pop {
+        tile = Rect::new(tile.x - 1, tile.y - 1, tile.width + 2, tile.height + 2);
+    }
+    buf.set_style(tile, Style::default().bg(background));
+
+    if exponent != 0 {
+        let number = (1u64 << exponent).to_string();
+        let column = tile.x + (tile.width - number.len() as u16) / 2;
+        buf.set_string(
+            column,
+            tile.y + tile.height / 2,
+            number,
+            Style::default()
+                .fg(text)
+                .bg(background)
+                .add_modifier(Modifier::BOLD),
+        );
+    }
+}
+
+fn arrow(direction: Directions) -> &'static str {
+    match direction {
+        Directions::Up => "↑",
+        Directions::Right => "→",
+        Directions::Down => "↓",
+        Directions::Left => "←",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{App, MIN_SIZE};
+    use crate::tui::tile_colors;
+    use ratatui::buffer::Buffer;
+    use ratatui::layout::Rect;
+    use ratatui::widgets::Widget;
+
+    fn render(app: &App) -> Buffer {
+        let area = Rect::new(0, 0, MIN_SIZE.0, MIN_SIZE.1 + 4);
+        let mut buffer = Buffer::empty(area);
+        app.render(area, &mut buffer);
+        buffer
+    }
+
+    fn text(buffer: &Buffer) -> String {
+        buffer.content().iter().map(|cell| cell.symbol()).collect()
+    }
+
+    mod render {
+        use super::*;
+
+        #[test]
+        fn shows_the_numbers_in_their_colors() {
+            let app = App::new(1);
+            let buffer = render(&app);
+
+            for (square, exponent) in app.game().observation().iter().enumerate() {
+                let x = 1 + (square % 4) as u16 * 8;
+                let y = 1 + (square / 4) as u16 * 4;
+                assert_eq!(buffer[(x, y)].bg, tile_colors(*exponent).0);
+            }
+        }
+
+        #[test]
+        fn shows_score_hint_and_history() {
+            let mut app = App::new(1);
+            app.play_hint();
+            app.play_hint();
+            let screen = text(&render(&app));
+
+            assert!(screen.contains(&format!("Score  {}", app.game().score())));
+            assert!(screen.contains("Hint"));
+            assert!(screen.contains("    2  "));
+        }
+
+        #[test]
+        fn asks_for_a_larger_terminal() {
+            let area = Rect::new(0, 0, 20, 5);
+            let mut buffer = Buffer::empty(area);
+            (&App::new(1)).render(area, &mut buffer);
+
+            assert!(text(&buffer).contains("Make the"));
+        }
+
+        #[test]
+        fn animation_ends_on_the_board() {
+            let mut app = App::new(5);
+            let start = render(&app);
+            let mut frames = 0;
+            assert!(app.play_hint());
+            while app.tick() {
+                render(&app);
+                frames += 1;
+            }
+
+            // The same move without an animation
+            let mut settled = App::new(5);
+            assert!(settled.play_hint());
+            settled.animation = None;
+
+            assert!(frames > 0);
+            assert_ne!(render(&app), start);
+            assert_eq!(render(&app), render(&settled));
+        }
+    }
+}
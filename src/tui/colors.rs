@@ -0,0 +1,49 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use ratatui::style::Color;
+
+use crate::base::*;
+
+/// The background of the board between the squares
+pub const BOARD: Color = Color::Rgb(0xbb, 0xad, 0xa0);
+
+/// The background of an empty square
+pub const EMPTY: Color = Color::Rgb(0xcd, 0xc1, 0xb4);
+
+const DARK_TEXT: Color = Color::Rgb(0x77, 0x6e, 0x65);
+const LIGHT_TEXT: Color = Color::Rgb(0xf9, 0xf6, 0xf2);
+
+/// The background and text colors of a number in the original game
+///
+/// Numbers above 2048 share one dark color.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::tui::{tile_colors, EMPTY};
+///
+/// let (background, _) = tile_colors(11);
+/// assert_ne!(background, EMPTY);
+/// assert_eq!(tile_colors(0).0, EMPTY);
+/// ```
+///
+pub fn tile_colors(exponent: BoardElement) -> (Color, Color) {
+    let background = match exponent {
+        0 => EMPTY,
+        1 => Color::Rgb(0xee, 0xe4, 0xda),
+        2 => Color::Rgb(0xed, 0xe0, 0xc8),
+        3 => Color::Rgb(0xf2, 0xb1, 0x79),
+        4 => Color::Rgb(0xf5, 0x95, 0x63),
+        5 => Color::Rgb(0xf6, 0x7c, 0x5f),
+        6 => Color::Rgb(0xf6, 0x5e, 0x3b),
+        7 => Color::Rgb(0xed, 0xcf, 0x72),
+        8 => Color::Rgb(0xed, 0xcc, 0x61),
+        9 => Color::Rgb(0xed, 0xc8, 0x50),
+        10 => Color::Rgb(0xed, 0xc5, 0x3f),
+        11 => Color::Rgb(0xed, 0xc2, 0x2e),
+        _ => Color::Rgb(0x3c, 0x3a, 0x32),
+    };
+    let text = if exponent <= 2 { DARK_TEXT } else { LIGHT_TEXT };
+    (background, text)
+}
@@ -0,0 +1,29 @@
+//! Full screen terminal frontend, enabled by the `tui` feature
+//!
+//! The [`App`] holds a game with its move history and a lookahead hint, and
+//! draws it as a [`ratatui`] widget with the colors of the original game and
+//! animated slides. Rendering only needs a buffer, so the app can be tested
+//! and inspected without a terminal.
+//!
+//! ```
+//! use game_2048_model::models::Directions;
+//! use game_2048_model::tui::{App, MIN_SIZE};
+//! use ratatui::buffer::Buffer;
+//! use ratatui::layout::Rect;
+//! use ratatui::widgets::Widget;
+//!
+//! let mut app = App::new(42);
+//! app.slide(Directions::Up);
+//!
+//! let area = Rect::new(0, 0, MIN_SIZE.0, MIN_SIZE.1);
+//! let mut buffer = Buffer::empty(area);
+//! app.render(area, &mut buffer);
+//! ```
+
+mod animation;
+mod app;
+mod colors;
+
+pub use animation::{tile_moves, TileMove};
+pub use app::{App, MIN_SIZE};
+pub use colors::{tile_colors, BOARD, EMPTY};
@@ -0,0 +1,307 @@
+#![warn(missing_docs)]
+#![warn(missing_doc_code_examples)]
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use wasm_bindgen::prelude::*;
+
+use crate::ai::Expectimax;
+use crate::base::*;
+use crate::encoding::MAX_EXPONENT;
+use crate::models::ArrayModel;
+
+/// A direction to slide towards, numbered like [`DIRECTIONS`]
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    /// Slide towards the top
+    Up = 0,
+    /// Slide towards the right
+    Right = 1,
+    /// Slide towards the bottom
+    Down = 2,
+    /// Slide towards the left
+    Left = 3,
+}
+
+impl From<Direction> for Directions {
+    fn from(direction: Direction) -> Self {
+        DIRECTIONS[direction as usize]
+    }
+}
+
+impl From<Directions> for Direction {
+    fn from(direction: Directions) -> Self {
+        match direction {
+            Directions::Up => Direction::Up,
+            Directions::Right => Direction::Right,
+            Directions::Down => Direction::Down,
+            Directions::Left => Direction::Left,
+        }
+    }
+}
+
+/// A game for JavaScript, running the same engine as [`ArrayModel`]
+///
+/// The board is given as the exponents of the numbers in array form, so 1 is
+/// a 2, 2 is a 4 and 0 an empty square. Spawns come from a generator seeded
+/// when the game is created, so a seed always gives the same game.
+///
+/// # Examples
+///
+/// ```
+/// use game_2048_model::wasm::{Direction, Game};
+///
+/// let mut game = Game::new(42);
+///
+/// if game.slide(Direction::Left) {
+///     game.spawn();
+/// }
+/// assert_eq!(game.board().len(), 16);
+/// ```
+///
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Game {
+    game: ArrayModel,
+    rng: StdRng,
+    score: u64,
+}
+
+#[wasm_bindgen]
+impl Game {
+    /// Start a new game with two spawned numbers
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::wasm::Game;
+    ///
+    /// let game = Game::new(7);
+    /// assert_eq!(game.board().iter().filter(|x| **x != 0).count(), 2);
+    /// ```
+    ///
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: u32) -> Game {
+        let mut game = Game::from_board(&[0; BOARD_SIZE * BOARD_SIZE], seed)
+            .expect("An empty board has the right size");
+        game.spawn();
+        game.spawn();
+        game
+    }
+
+    /// Load a game from the exponents of its numbers in array form
+    ///
+    /// Returns `undefined` if the board does not have 16 squares or has an
+    /// exponent above 17, the largest number that fits on a board.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::wasm::Game;
+    ///
+    /// let game = Game::from_board(&[1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 7).unwrap();
+    /// assert_eq!(game.score(), 0.0);
+    ///
+    /// assert!(Game::from_board(&[18, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 7).is_none());
+    /// ```
+    ///
+    #[wasm_bindgen(js_name = fromBoard)]
+    pub fn from_board(board: &[u8], seed: u32) -> Option<Game> {
+        let mut array: ArrayBoard = [0; BOARD_SIZE * BOARD_SIZE];
+        if board.len() != array.len() || board.iter().any(|x| *x > MAX_EXPONENT) {
+            return None;
+        }
+        array.copy_from_slice(board);
+        Some(Game {
+            game: ArrayModel::from(array),
+            rng: StdRng::seed_from_u64(u64::from(seed)),
+            score: 0,
+        })
+    }
+
+    /// Slide towards the direction, returns false if the board did not change
+    ///
+    /// No number is spawned, call [`Game::spawn`] after a successful slide.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::wasm::{Direction, Game};
+    ///
+    /// let mut game = Game::from_board(&[1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 7).unwrap();
+    ///
+    /// assert!(game.slide(Direction::Left));
+    /// assert_eq!(game.score(), 4.0);
+    /// assert!(!game.slide(Direction::Left));
+    /// ```
+    ///
+    pub fn slide(&mut self, direction: Direction) -> bool {
+        let before = self.game.as_array();
+        if self.game.slide(direction.into()).is_none() {
+            return false;
+        }
+        self.score += slide_score(&before, &self.game.as_array());
+        true
+    }
+
+    /// Spawn a 2 or a 4 on a random empty square, returns false if the board is full
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::wasm::Game;
+    ///
+    /// let mut game = Game::from_board(&[0; 16], 7).unwrap();
+    ///
+    /// assert!(game.spawn());
+    /// assert_eq!(game.board().iter().filter(|x| **x != 0).count(), 1);
+    /// ```
+    ///
+    pub fn spawn(&mut self) -> bool {
+        self.game.random(&mut self.rng).is_ok()
+    }
+
+    /// The exponents of the numbers in array form, a `Uint8Array` in JavaScript
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::wasm::Game;
+    ///
+    /// assert_eq!(Game::from_board(&[0; 16], 7).unwrap().board(), vec![0; 16]);
+    /// ```
+    ///
+    pub fn board(&self) -> Vec<u8> {
+        self.game.as_array().to_vec()
+    }
+
+    /// The score gained by merges
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::wasm::Game;
+    ///
+    /// assert_eq!(Game::new(7).score(), 0.0);
+    /// ```
+    ///
+    pub fn score(&self) -> f64 {
+        self.score as f64
+    }
+
+    /// Whether no direction changes the board
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::wasm::Game;
+    ///
+    /// assert!(!Game::new(7).is_over());
+    /// ```
+    ///
+    #[wasm_bindgen(js_name = isOver)]
+    pub fn is_over(&self) -> bool {
+        let board = self.game.as_array();
+        DIRECTIONS
+            .iter()
+            .all(|direction| ArrayModel::from(board).slide(*direction).is_none())
+    }
+
+    /// The best direction found by a two move [`Expectimax`] search,
+    /// `undefined` if the game is over
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use game_2048_model::wasm::Game;
+    ///
+    /// assert!(Game::new(7).hint().is_some());
+    /// ```
+    ///
+    pub fn hint(&self) -> Option<Direction> {
+        Expectimax::default()
+            .depth(2)
+            .best_direction(&self.game)
+            .map(Direction::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Direction, Game};
+    use crate::models::{ArrayModel, Directions, Model, DIRECTIONS};
+
+    mod slide {
+        use super::*;
+
+        #[test]
+        fn matches_the_engine() {
+            let mut game = Game::new(3);
+            let mut turn = 0;
+            while !game.is_over() {
+                let direction = DIRECTIONS[turn % 4];
+                let mut expected = ArrayModel::from({
+                    let mut board = [0; 16];
+                    board.copy_from_slice(&game.board());
+                    board
+                });
+                let moved = expected.slide(direction).is_some();
+
+                assert_eq!(game.slide(Direction::from(direction)), moved);
+                assert_eq!(game.board(), expected.as_array().to_vec());
+                if moved {
+                    assert!(game.spawn());
+                }
+                turn += 1;
+            }
+            assert!(game.score() > 0.0);
+        }
+    }
+
+    mod from_board {
+        use super::*;
+
+        #[test]
+        fn rejects_exponents_off_the_board() {
+            let mut board = [0; 16];
+            board[5] = 17;
+            assert!(Game::from_board(&board, 1).is_some());
+
+            board[5] = 18;
+            assert!(Game::from_board(&board, 1).is_none());
+            board[5] = 255;
+            assert!(Game::from_board(&board, 1).is_none());
+        }
+
+        #[test]
+        fn rejects_boards_of_other_sizes() {
+            assert!(Game::from_board(&[0; 15], 1).is_none());
+            assert!(Game::from_board(&[0; 17], 1).is_none());
+        }
+    }
+
+    mod direction {
+        use super::*;
+
+        #[test]
+        fn numbered_like_directions() {
+            for direction in DIRECTIONS.iter() {
+                assert_eq!(Directions::from(Direction::from(*direction)), *direction);
+            }
+        }
+    }
+
+    mod hint {
+        use super::*;
+
+        #[test]
+        fn none_when_over() {
+            let game =
+                Game::from_board(&[1, 2, 1, 2, 2, 1, 2, 1, 1, 2, 1, 2, 2, 1, 2, 1], 0).unwrap();
+
+            assert!(game.is_over());
+            assert_eq!(game.hint(), None);
+        }
+    }
+}
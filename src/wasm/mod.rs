@@ -0,0 +1,20 @@
+//! WebAssembly bindings, enabled by the `wasm` feature
+//!
+//! [`Game`] wraps the engine in an object JavaScript can use directly, so
+//! browser frontends play by the same rules as the Rust code. Build it with
+//! `cargo rustc --release --lib --crate-type cdylib --target wasm32-unknown-unknown --features wasm`
+//! and generate the JavaScript glue with `wasm-bindgen`.
+//!
+//! ```
+//! use game_2048_model::wasm::{Direction, Game};
+//!
+//! let mut game = Game::new(1);
+//! if let Some(direction) = game.hint() {
+//!     assert!(game.slide(direction));
+//!     game.spawn();
+//! }
+//! ```
+
+mod game;
+
+pub use game::{Direction, Game};